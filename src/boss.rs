@@ -0,0 +1,162 @@
+use super::consts::*;
+use super::entity::{BulletBase, Projectile, ShotPattern};
+use super::movement::{Movement, Pattern};
+
+/// Where stage bosses come in to and hold position around.
+pub const BOSS_HOME: [f64; 2] = [WIDTH as f64 / 2., 96.];
+
+/// How a stage boss moves during a phase.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhaseMovement{
+    /// Comes to a halt at BOSS_HOME
+    Hover,
+    /// Sways from side to side
+    Sway,
+    /// Circles around BOSS_HOME
+    Orbit,
+}
+
+impl PhaseMovement{
+    pub fn movement(&self) -> Movement{
+        Movement::new(match self {
+            PhaseMovement::Hover => Pattern::SwoopIn{stop: BOSS_HOME},
+            PhaseMovement::Sway => Pattern::Weave{velo: [0., 0.], amplitude: 96., period: 240.},
+            PhaseMovement::Orbit => Pattern::Orbit{center: BOSS_HOME, radius: 48., angular_speed: 0.02},
+        })
+    }
+}
+
+pub struct BossPhase{
+    /// The phase begins when health falls to this percentage of the maximum
+    pub threshold: i32,
+    pub pattern: ShotPattern,
+    /// average frames between volleys
+    pub period: u32,
+    pub bullet: fn(BulletBase) -> Projectile,
+    pub movement: PhaseMovement,
+}
+
+/// Definition of a boss that arrives at the end of a wave.
+pub struct BossKind{
+    pub name: &'static str,
+    pub health: i32,
+    pub score: u32,
+    /// in order of decreasing threshold, the first one starting at 100
+    pub phases: &'static [BossPhase],
+}
+
+impl BossKind{
+    /// Index of the phase for the remaining health.
+    pub fn phase_for(&self, health: i32) -> usize{
+        self.phases.iter().rposition(|phase| health * 100 <= phase.threshold * self.health).unwrap_or(0)
+    }
+}
+
+/// Bosses appear in this order, one per wave.
+pub static BOSSES: [BossKind; 2] = [
+    BossKind{name: "Twin Fang", health: 600, score: 100, phases: &[
+        BossPhase{threshold: 100, pattern: ShotPattern::NWay(5), period: 48,
+            bullet: Projectile::new_enemy_bullet, movement: PhaseMovement::Hover},
+        BossPhase{threshold: 60, pattern: ShotPattern::Ring(16), period: 64,
+            bullet: Projectile::new_phase, movement: PhaseMovement::Sway},
+        BossPhase{threshold: 25, pattern: ShotPattern::Lead, period: 12,
+            bullet: Projectile::new_enemy_bullet, movement: PhaseMovement::Orbit},
+    ]},
+    BossKind{name: "Spiral Core", health: 800, score: 150, phases: &[
+        BossPhase{threshold: 100, pattern: ShotPattern::Ring(12), period: 64,
+            bullet: Projectile::new_spiral, movement: PhaseMovement::Hover},
+        BossPhase{threshold: 70, pattern: ShotPattern::NWay(7), period: 40,
+            bullet: Projectile::new_phase, movement: PhaseMovement::Sway},
+        BossPhase{threshold: 40, pattern: ShotPattern::Ring(20), period: 48,
+            bullet: Projectile::new_spiral, movement: PhaseMovement::Orbit},
+        BossPhase{threshold: 15, pattern: ShotPattern::Aimed, period: 8,
+            bullet: Projectile::new_enemy_bullet, movement: PhaseMovement::Sway},
+    ]},
+];
+
+/// Score for defeating a boss `elapsed` frames after its arrival.
+pub fn time_bonus(elapsed: u32) -> u32{
+    BOSS_TIME_LIMIT.saturating_sub(elapsed) / 60 * BOSS_TIME_BONUS
+}
+
+#[test]
+fn test_boss_kinds() {
+    for kind in BOSSES.iter() {
+        assert_eq!(kind.phases[0].threshold, 100);
+        assert!(kind.phases.windows(2).all(|w| w[1].threshold < w[0].threshold));
+        assert_eq!(kind.phase_for(kind.health), 0);
+        assert_eq!(kind.phase_for(0), kind.phases.len() - 1);
+    }
+    assert_eq!(BOSSES[0].phase_for(BOSSES[0].health * 60 / 100), 1);
+    assert_eq!(BOSSES[0].phase_for(BOSSES[0].health * 60 / 100 + 1), 0);
+}
+
+#[test]
+fn test_boss_phases() {
+    use super::entity::{Enemy, StageBoss, Entity};
+    use std::collections::HashMap;
+    let mut id_gen = 0;
+    let mut rng = rand::thread_rng();
+    let mut bullets = HashMap::new();
+    let player = Entity::new(&mut id_gen, [100., 400.], [0., 0.]);
+    let kind = &BOSSES[0];
+    let mut enemy = Enemy::StageBoss(StageBoss::new(&mut id_gen, kind, 0));
+    assert!(enemy.is_boss());
+
+    let phase = |enemy: &Enemy| enemy.as_stage_boss().unwrap().phase;
+    enemy.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+    assert_eq!(phase(&enemy), 0);
+    assert_eq!(enemy.shot_pattern(0).unwrap().0, kind.phases[0].pattern);
+
+    // Crossing a threshold changes the attack and the movement
+    enemy.damage(kind.health / 2);
+    enemy.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+    assert_eq!(phase(&enemy), 1);
+    assert_eq!(enemy.shot_pattern(0).unwrap().0, kind.phases[1].pattern);
+
+    enemy.damage(kind.health * 2 / 5);
+    enemy.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+    assert_eq!(phase(&enemy), 2);
+
+    // Phases never go back, even if health is restored
+    enemy.damage(-kind.health);
+    enemy.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+    assert_eq!(phase(&enemy), 2);
+
+    // A heavy hit on a fresh boss skips straight to the last phase
+    let mut enemy = Enemy::StageBoss(StageBoss::new(&mut id_gen, kind, 0));
+    enemy.damage(kind.health * (100 - kind.phases.last().unwrap().threshold) / 100 + 1);
+    enemy.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+    assert_eq!(phase(&enemy), kind.phases.len() - 1);
+}
+
+#[test]
+fn test_boss_time_out() {
+    use super::entity::{Enemy, StageBoss, Entity, DeathReason};
+    use std::collections::HashMap;
+    let mut id_gen = 0;
+    let mut rng = rand::thread_rng();
+    let mut bullets = HashMap::new();
+    let player = Entity::new(&mut id_gen, [100., 400.], [0., 0.]);
+    let mut enemy = Enemy::StageBoss(StageBoss::new(&mut id_gen, &BOSSES[0], 0));
+
+    // Holds the stage until the time limit
+    for time in 0..BOSS_TIME_LIMIT {
+        assert!(enemy.animate(&mut id_gen, &mut bullets, &mut rng, time, &player, true, 0).is_none());
+    }
+
+    // Then flies away, even if a phase changes on the way
+    enemy.damage(BOSSES[0].health / 2);
+    let left = (BOSS_TIME_LIMIT..BOSS_TIME_LIMIT + 600).any(|time| {
+        if let Some(DeathReason::RangeOut) = enemy.animate(&mut id_gen, &mut bullets, &mut rng, time, &player, true, 0) { true } else { false }
+    });
+    assert!(left);
+}
+
+#[test]
+fn test_time_bonus() {
+    assert_eq!(time_bonus(0), BOSS_TIME_LIMIT / 60 * BOSS_TIME_BONUS);
+    assert!(time_bonus(600) < time_bonus(0));
+    assert_eq!(time_bonus(BOSS_TIME_LIMIT), 0);
+    assert_eq!(time_bonus(BOSS_TIME_LIMIT * 2), 0);
+}
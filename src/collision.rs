@@ -0,0 +1,270 @@
+use vecmath::*;
+
+/// Collision shape of an Entity, defined in the entity's local coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape{
+    Circle(f64),
+    /// Box with half extents that ignores the entity's rotation
+    Aabb([f64; 2]),
+    /// Box with half extents that rotates with the entity
+    Obb([f64; 2]),
+    /// Segment along the local y axis with given half length, inflated by radius
+    Capsule{half_length: f64, radius: f64},
+}
+
+impl Shape{
+    /// Places the shape in world coordinates.
+    pub fn at(&self, pos: [f64; 2], rotation: f64) -> Collider{
+        let (s, c) = rotation.sin_cos();
+        let transform = |v: [f64; 2]| [pos[0] + c * v[0] - s * v[1], pos[1] + s * v[0] + c * v[1]];
+        match *self {
+            Shape::Circle(radius) => Collider::new(&[pos], radius),
+            Shape::Aabb(half) => Collider::aabb([pos[0] - half[0], pos[1] - half[1], pos[0] + half[0], pos[1] + half[1]]),
+            Shape::Obb(half) => Collider::new(&[
+                transform([-half[0], -half[1]]),
+                transform([half[0], -half[1]]),
+                transform([half[0], half[1]]),
+                transform([-half[0], half[1]]),
+            ], 0.),
+            Shape::Capsule{half_length, radius} => Collider::segment(
+                transform([0., -half_length]),
+                transform([0., half_length]),
+                radius),
+        }
+    }
+}
+
+/// Maximum number of vertices of a core, enough for a swept box.
+const MAX_VERTICES: usize = 8;
+
+/// A shape in world coordinates, represented as a convex core (a point, a segment or
+/// a convex polygon) inflated by a radius.  All pairwise tests reduce
+/// to the distance between the two cores.
+#[derive(Clone, Copy, Debug)]
+pub struct Collider{
+    verts: [[f64; 2]; MAX_VERTICES],
+    count: usize,
+    pub radius: f64,
+}
+
+impl Collider{
+    fn new(verts: &[[f64; 2]], radius: f64) -> Self{
+        let mut ret = Self{verts: [[0.; 2]; MAX_VERTICES], count: verts.len(), radius};
+        ret.verts[..verts.len()].copy_from_slice(verts);
+        ret
+    }
+
+    /// Axis-aligned rectangle given by [left, top, right, bottom].
+    pub fn aabb(rect: [f64; 4]) -> Self{
+        Self::new(&[[rect[0], rect[1]], [rect[2], rect[1]], [rect[2], rect[3]], [rect[0], rect[3]]], 0.)
+    }
+
+    /// A capsule spanning from `a` to `b`.
+    pub fn segment(a: [f64; 2], b: [f64; 2], radius: f64) -> Self{
+        Self::new(&[a, b], radius)
+    }
+
+    /// Returns the volume this collider covers while moving by `delta`, which is the
+    /// convex hull of the core at both ends inflated by the same radius.
+    /// Testing against it instead of the end position prevents fast objects from
+    /// tunneling through thin ones between frames.
+    pub fn sweep(&self, delta: [f64; 2]) -> Self{
+        let mut points = [[0.; 2]; MAX_VERTICES * 2];
+        let count = self.count * 2;
+        for (i, v) in self.vertices().iter().enumerate() {
+            points[i * 2] = *v;
+            points[i * 2 + 1] = vec2_add(*v, delta);
+        }
+        let points = &mut points[..count];
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        // Andrew's monotone chain
+        let mut hull = [[0.; 2]; MAX_VERTICES * 2 + 1];
+        let mut n = 0;
+        for pass in 0..2 {
+            let start = n;
+            for i in 0..count {
+                let p = if pass == 0 { points[i] } else { points[count - 1 - i] };
+                while start + 2 <= n && vec2_cross(vec2_sub(hull[n - 1], hull[n - 2]), vec2_sub(p, hull[n - 2])) <= 0. {
+                    n -= 1;
+                }
+                hull[n] = p;
+                n += 1;
+            }
+            // The last point of each chain is the first point of the other
+            n -= 1;
+        }
+        let n = n.max(1).min(MAX_VERTICES);
+        Self::new(&hull[..n], self.radius)
+    }
+
+    fn vertices(&self) -> &[[f64; 2]]{
+        &self.verts[..self.count]
+    }
+
+    /// Iterates edges of the core.  A point yields a degenerate edge so that
+    /// point-to-edge distances work uniformly.
+    fn edges<'a>(&'a self) -> impl Iterator<Item = ([f64; 2], [f64; 2])> + 'a{
+        let count = self.count;
+        let edge_count = if count < 3 { 1 } else { count };
+        (0..edge_count).map(move |i| (self.verts[i], self.verts[(i + 1) % count]))
+    }
+
+    fn contains_point(&self, p: [f64; 2]) -> bool{
+        if self.count < 3 {
+            return false;
+        }
+        let mut sign = 0.;
+        for (a, b) in self.edges() {
+            let cross = vec2_cross(vec2_sub(b, a), vec2_sub(p, a));
+            if cross * sign < 0. {
+                return false;
+            }
+            if cross != 0. {
+                sign = cross;
+            }
+        }
+        true
+    }
+
+    /// Distance between the cores, zero if they overlap.
+    fn core_distance(&self, other: &Self) -> f64{
+        if other.vertices().iter().any(|v| self.contains_point(*v))
+            || self.vertices().iter().any(|v| other.contains_point(*v)) {
+            return 0.;
+        }
+        let mut best = std::f64::INFINITY;
+        for (a, b) in self.edges() {
+            for (c, d) in other.edges() {
+                if segments_intersect(a, b, c, d) {
+                    return 0.;
+                }
+                best = best
+                    .min(point_segment_distance(a, c, d))
+                    .min(point_segment_distance(b, c, d))
+                    .min(point_segment_distance(c, a, b))
+                    .min(point_segment_distance(d, a, b));
+            }
+        }
+        best
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool{
+        let distance = self.core_distance(other);
+        distance == 0. || distance < self.radius + other.radius
+    }
+
+    /// Returns [left, top, right, bottom] enclosing the collider.
+    pub fn bounding_box(&self) -> [f64; 4]{
+        let r = self.radius;
+        self.vertices().iter().fold([std::f64::INFINITY, std::f64::INFINITY, -std::f64::INFINITY, -std::f64::INFINITY],
+            |bb, v| [bb[0].min(v[0] - r), bb[1].min(v[1] - r), bb[2].max(v[0] + r), bb[3].max(v[1] + r)])
+    }
+}
+
+fn point_segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64{
+    let ab = vec2_sub(b, a);
+    let len2 = vec2_square_len(ab);
+    let t = if len2 == 0. { 0. } else { (vec2_dot(vec2_sub(p, a), ab) / len2).max(0.).min(1.) };
+    vec2_len(vec2_sub(p, vec2_add(a, vec2_scale(ab, t))))
+}
+
+fn segments_intersect(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> bool{
+    let d1 = vec2_cross(vec2_sub(b, a), vec2_sub(c, a));
+    let d2 = vec2_cross(vec2_sub(b, a), vec2_sub(d, a));
+    let d3 = vec2_cross(vec2_sub(d, c), vec2_sub(a, c));
+    let d4 = vec2_cross(vec2_sub(d, c), vec2_sub(b, c));
+    // Collinear or touching cases are covered by the point-segment distances
+    d1 * d2 < 0. && d3 * d4 < 0.
+}
+
+#[test]
+fn test_circle_circle() {
+    let a = Shape::Circle(4.).at([0., 0.], 0.);
+    assert!(a.intersects(&Shape::Circle(2.).at([5.9, 0.], 0.)));
+    assert!(!a.intersects(&Shape::Circle(2.).at([6.1, 0.], 0.)));
+    assert!(!a.intersects(&Shape::Circle(2.).at([4.3, 4.3], 0.)));
+}
+
+#[test]
+fn test_circle_box() {
+    let aabb = Shape::Aabb([4., 2.]).at([0., 0.], 1.);
+    // Rotation is ignored by Aabb
+    assert!(aabb.intersects(&Shape::Circle(1.).at([4.9, 0.], 0.)));
+    assert!(!aabb.intersects(&Shape::Circle(1.).at([0., 3.1], 0.)));
+    // Near the corner the distance is Euclidean
+    assert!(!aabb.intersects(&Shape::Circle(1.).at([4.8, 2.8], 0.)));
+
+    // The same box rotated by 90 degrees is tall instead of wide
+    let obb = Shape::Obb([4., 2.]).at([0., 0.], std::f64::consts::FRAC_PI_2);
+    assert!(!obb.intersects(&Shape::Circle(1.).at([4.9, 0.], 0.)));
+    assert!(obb.intersects(&Shape::Circle(1.).at([0., 4.9], 0.)));
+    // A circle entirely inside the box
+    assert!(obb.intersects(&Shape::Circle(0.5).at([0.5, 0.5], 0.)));
+}
+
+#[test]
+fn test_box_box() {
+    let a = Shape::Aabb([2., 2.]).at([0., 0.], 0.);
+    assert!(a.intersects(&Shape::Aabb([2., 2.]).at([3.9, 0.], 0.)));
+    assert!(!a.intersects(&Shape::Aabb([2., 2.]).at([4.1, 0.], 0.)));
+
+    // A diamond reaches further along the diagonal axis than the square
+    let diamond = Shape::Obb([2., 2.]).at([4.5, 0.], std::f64::consts::FRAC_PI_4);
+    assert!(a.intersects(&diamond));
+    let diamond = Shape::Obb([2., 2.]).at([3.9, 3.9], std::f64::consts::FRAC_PI_4);
+    assert!(!a.intersects(&diamond));
+
+    // Crossing boxes with no vertex inside each other
+    let wide = Shape::Aabb([10., 1.]).at([0., 0.], 0.);
+    let tall = Shape::Aabb([1., 10.]).at([0., 0.], 0.);
+    assert!(wide.intersects(&tall));
+}
+
+#[test]
+fn test_capsule() {
+    let capsule = Shape::Capsule{half_length: 10., radius: 1.}.at([0., 0.], 0.);
+    assert!(capsule.intersects(&Shape::Circle(1.).at([1.9, 9.], 0.)));
+    assert!(!capsule.intersects(&Shape::Circle(1.).at([2.1, 9.], 0.)));
+    assert!(!capsule.intersects(&Shape::Circle(1.).at([0., 12.1], 0.)));
+
+    // Crossing capsules
+    let crossing = Shape::Capsule{half_length: 10., radius: 0.}.at([0., 0.], std::f64::consts::FRAC_PI_2);
+    assert!(capsule.intersects(&crossing));
+
+    // Capsule passing through a box without any vertex inside
+    let aabb = Collider::aabb([-2., -2., 2., 2.]);
+    assert!(aabb.intersects(&Collider::segment([-5., 0.], [5., 0.], 0.)));
+    assert!(!aabb.intersects(&Collider::segment([-5., 3.5], [5., 3.5], 1.)));
+    assert!(aabb.intersects(&Collider::segment([-5., 2.5], [5., 2.5], 1.)));
+}
+
+#[test]
+fn test_sweep() {
+    // A small circle moving fast passes over a thin box between the end positions
+    let wall = Collider::aabb([-10., -1., 10., 1.]);
+    let bullet = Shape::Circle(1.).at([0., 10.], 0.);
+    assert!(!bullet.intersects(&wall));
+    assert!(!Shape::Circle(1.).at([0., -10.], 0.).intersects(&wall));
+    assert!(bullet.sweep([0., -20.]).intersects(&wall));
+    assert!(!bullet.sweep([0., -5.]).intersects(&wall));
+    assert!(!bullet.sweep([30., -20.]).intersects(&wall));
+
+    // A swept box is a hexagon
+    let swept = Shape::Aabb([1., 1.]).at([0., 0.], 0.).sweep([10., 10.]);
+    assert_eq!(swept.count, 6);
+    assert_eq!(swept.bounding_box(), [-1., -1., 11., 11.]);
+    assert!(swept.intersects(&Shape::Circle(0.5).at([5., 5.], 0.)));
+    assert!(!swept.intersects(&Shape::Circle(0.5).at([8., 2.], 0.)));
+
+    // Sweeping by zero leaves the shape as it is
+    let still = Shape::Capsule{half_length: 3., radius: 1.}.at([0., 0.], 0.).sweep([0., 0.]);
+    assert_eq!(still.count, 2);
+    assert_eq!(still.bounding_box(), [-1., -4., 1., 4.]);
+}
+
+#[test]
+fn test_bounding_box() {
+    let bb = Shape::Capsule{half_length: 3., radius: 1.}.at([10., 20.], 0.).bounding_box();
+    assert_eq!(bb, [9., 16., 11., 24.]);
+}
@@ -4,15 +4,92 @@ pub const WIDTH: u32 = WINDOW_WIDTH * 3 / 4;
 pub const HEIGHT: u32 = WINDOW_HEIGHT;
 
 pub const PLAYER_SPEED: f64 = 2.;
+pub const PLAYER_FOCUS_SPEED: f64 = 1.;
+pub const PLAYER_FOCUS_SPREAD: f64 = 0.5;
 pub const PLAYER_SIZE: f64 = 16.;
+pub const PLAYER_HIT_RADIUS: f64 = 2.;
+pub const PLAYER_PICKUP_RADIUS: f64 = 12.;
+pub const PLAYER_GRAZE_RADIUS: f64 = 16.;
+pub const GRAZE_SCORE: u32 = 1;
+
+pub const ENEMY1_SCORE: u32 = 1;
+pub const BOSS_SCORE: u32 = 10;
+pub const SHIELDED_BOSS_SCORE: u32 = 10;
+pub const SPIRAL_ENEMY_SCORE: u32 = 1;
+pub const TURRET_SCORE: u32 = 3;
+pub const KAMIKAZE_SCORE: u32 = 2;
+pub const SPLITTER_SCORE: u32 = 3;
+pub const CARRIER_SCORE: u32 = 15;
+pub const COMBO_WINDOW: u32 = 96;
+pub const COMBO_STEP: u32 = 8;
+pub const COMBO_MAX_MULTIPLIER: u32 = 8;
+pub const SCORE_POPUP_TIME: u32 = 48;
+pub const MEDAL_SCORE: u32 = 10;
+pub const MEDAL_MAX_CHAIN: u32 = 10;
 pub const PLAYER_INVINCIBLE_TIME: u32 = 128;
+pub const PLAYER_SPAWN_POS: [f64; 2] = [240., 400.];
+pub const PLAYER_DEATH_TIME: u32 = 64;
+pub const PLAYER_RESPAWN_CLEAR_RADIUS: f64 = 128.;
+pub const DEATH_POWER_LOSS_PERCENT: u32 = 50;
+pub const DEATH_SCATTER_MAX_ITEMS: usize = 16;
+pub const ITEM_SPEED: f64 = 1.;
+pub const ITEM_GRAVITY: f64 = 0.05;
+pub const ITEM_DRAG: f64 = 0.95;
+pub const ITEM_MAGNET_RADIUS: f64 = 48.;
+pub const ITEM_HOMING_SPEED: f64 = 6.;
+pub const ITEM_STEERING: f64 = 0.2;
+pub const ITEM_COLLECT_LINE: f64 = 120.;
 pub const PLAYER_LIVES: u32 = 3;
+pub const PLAYER_LIVES_MAX: u32 = 5;
+pub const EXTEND_SCORE_INTERVAL: u32 = 500;
+pub const EXTEND_NOTIFY_TIME: u32 = 120;
+pub const PLAYER_BOMBS: u32 = 3;
+pub const PLAYER_BOMBS_MAX: u32 = 5;
+pub const PLAYER_SHIELD_TIME: u32 = 600;
+pub const BOMB_TIME: u32 = 64;
+pub const BOMB_INVINCIBLE_TIME: u32 = 128;
+pub const BOMB_DAMAGE: i32 = 32;
 pub const ENEMY_SIZE: f64 = 8.;
-pub const BULLET_SIZE: f64 = 8.;
+pub const BOSS_SIZE: f64 = 12.;
+pub const TURRET_SIZE: f64 = 10.;
+pub const KAMIKAZE_SIZE: f64 = 6.;
+pub const KAMIKAZE_ACCEL: f64 = 0.08;
+pub const KAMIKAZE_MAX_SPEED: f64 = 5.;
+pub const SPLITTER_SIZE: f64 = 12.;
+pub const SPLITTER_CHILDREN: u32 = 3;
+pub const CARRIER_SIZE: f64 = 18.;
+pub const CARRIER_LAUNCH_PERIOD: u32 = 180;
+pub const STAGE_BOSS_SIZE: f64 = 24.;
+pub const BOSS_TIME_LIMIT: u32 = 3600;
+pub const BOSS_TIME_BONUS: u32 = 10;
+pub const ENEMY_SHOT_SPEED: f64 = 1.5;
+pub const ENEMY_SHOT_SPEED_PER_LEVEL: f64 = 0.125;
+pub const ENEMY_SHOT_SPEED_MAX: f64 = 3.;
+pub const ENEMY_NWAY_SPREAD: f64 = 0.2;
 pub const BULLET_SPEED: f64 = 5.;
 pub const MISSILE_SPEED: f64 = 3.;
+pub const WEAPON_POWER_PER_LEVEL: u32 = 16;
+pub const WEAPON_ENERGY_MAX: u32 = 256;
+pub const WEAPON_ENERGY_RECHARGE: u32 = 1;
+pub const CHARGE_THRESHOLD: u32 = 32;
+pub const CHARGE_MAX: u32 = 128;
+pub const CHARGE_SHOT_SPEED: f64 = 6.;
+pub const OPTION_MAX: usize = 4;
+pub const OPTION_LEVELS: u32 = 2;
+pub const OPTION_TRAIL_SPACING: usize = 12;
+pub const OPTION_FOLLOW_RATE: f64 = 0.25;
+pub const LOCK_SWEEP_SPEED: f64 = 6.;
+pub const LOCK_RADIUS: f64 = 24.;
+pub const LOCK_MAX_TARGETS: u32 = 2;
+pub const LOCK_BONUS_SCORE: u32 = 2;
+pub const LASER_SPEED: f64 = 8.;
+pub const LASER_STEERING: f64 = 0.3;
 pub const LIGHT_WIDTH: f64 = 3.;
+pub const LIGHT_PIERCE_LEVELS: u32 = 2;
 
 pub const LIGHTNING_ACCEL: f64 = 8.0;
 pub const LIGHTNING_FEEDBACK: f64 = 0.1;
-pub const LIGHTNING_VERTICES: u32 = 32;
\ No newline at end of file
+pub const LIGHTNING_VERTICES: u32 = 32;
+pub const LIGHTNING_CHAIN_RADIUS: f64 = 96.;
+pub const LIGHTNING_CHAIN_FALLOFF: i32 = 70;
+pub const LIGHTNING_ARC_SEGMENTS: u32 = 8;
\ No newline at end of file
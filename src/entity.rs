@@ -7,6 +7,10 @@ use vecmath::*;
 use rand::prelude::*;
 
 use super::consts::*;
+use super::collision::{Shape, Collider};
+use super::weapon::{Weapon, WEAPONS};
+use super::movement::Movement;
+use super::boss::{BossKind, BossPhase};
 
 pub struct Assets{
     pub bg: G2dTexture,
@@ -14,6 +18,10 @@ pub struct Assets{
     pub boss_tex: G2dTexture,
     pub enemy_tex: G2dTexture,
     pub spiral_enemy_tex: G2dTexture,
+    pub turret_tex: G2dTexture,
+    pub kamikaze_tex: G2dTexture,
+    pub splitter_tex: G2dTexture,
+    pub carrier_tex: G2dTexture,
     pub player_tex: G2dTexture,
     pub shield_tex: G2dTexture,
     pub ebullet_tex: G2dTexture,
@@ -26,6 +34,10 @@ pub struct Assets{
     pub sphere_tex: G2dTexture,
     pub power_tex: G2dTexture,
     pub power2_tex: G2dTexture,
+    pub bomb_tex: G2dTexture,
+    pub shield_item_tex: G2dTexture,
+    pub medal_tex: G2dTexture,
+    pub full_power_tex: G2dTexture,
 }
 
 impl Assets{
@@ -52,6 +64,10 @@ impl Assets{
             boss_tex: load_texture("boss.png"),
             enemy_tex: load_texture("enemy.png"),
             spiral_enemy_tex: load_texture("spiral-enemy.png"),
+            turret_tex: load_texture("turret.png"),
+            kamikaze_tex: load_texture("kamikaze.png"),
+            splitter_tex: load_texture("splitter.png"),
+            carrier_tex: load_texture("carrier.png"),
             player_tex: load_texture("player.png"),
             shield_tex: load_texture("shield.png"),
             ebullet_tex: load_texture("ebullet.png"),
@@ -64,6 +80,10 @@ impl Assets{
             sphere_tex: load_texture("sphere.png"),
             power_tex: load_texture("power.png"),
             power2_tex: load_texture("power2.png"),
+            bomb_tex: load_texture("bomb.png"),
+            shield_item_tex: load_texture("shield-item.png"),
+            medal_tex: load_texture("medal.png"),
+            full_power_tex: load_texture("full-power.png"),
         }, glyphs)
     }
 }
@@ -76,6 +96,7 @@ pub struct Entity{
     pub health: i32,
     pub rotation: f32,
     pub blend: Option<Blend>,
+    pub shape: Shape,
 }
 
 pub enum DeathReason{
@@ -108,6 +129,7 @@ impl Entity{
             health: 1,
             rotation: 0.,
             blend: None,
+            shape: Shape::Aabb([ENEMY_SIZE, ENEMY_SIZE]),
         }
     }
 
@@ -126,6 +148,16 @@ impl Entity{
         self
     }
 
+    pub fn shape(mut self, shape: Shape) -> Self{
+        self.shape = shape;
+        self
+    }
+
+    /// Returns the collision shape placed at the current position and rotation.
+    pub fn collider(&self) -> Collider{
+        self.shape.at(self.pos, self.rotation as f64)
+    }
+
     /// Returns None if the Entity survived this frame.
     /// Otherwise returns Some(reason) where reason is DeathReason.
     pub fn animate(&mut self) -> Option<DeathReason>{
@@ -159,10 +191,23 @@ impl Entity{
         image.draw(tex2, &draw_state, (Matrix(context.transform) * Matrix(translate) * Matrix(rotmat) * Matrix(centerize)).0, g);
     }
 
+    /// Returns the volume covered by the collision shape while moving by the velocity
+    /// in this frame.
+    pub fn swept_collider(&self) -> Collider{
+        self.collider().sweep(self.velo)
+    }
+
+    /// Tests for a near miss, which is the projectile passing through the graze
+    /// radius around the player.  Call it after hits_player() missed.
+    pub fn grazes_player(&self, player: &Self) -> bool {
+        self.swept_collider().intersects(&Shape::Circle(PLAYER_GRAZE_RADIUS).at(player.pos, 0.))
+    }
+
+    /// The player's shape is the small core of the ship, so the projectile has to
+    /// reach it rather than anywhere on its sprite.  The projectile is swept along
+    /// its velocity so that it cannot jump over the core.
     pub fn hits_player(&self, player: &Self) -> Option<DeathReason> {
-        let e = &player;
-        if self.pos[0] - BULLET_SIZE < e.pos[0] + ENEMY_SIZE && e.pos[0] - ENEMY_SIZE < self.pos[0] + BULLET_SIZE &&
-            self.pos[1] - BULLET_SIZE < e.pos[1] + ENEMY_SIZE && e.pos[1] - ENEMY_SIZE < self.pos[1] + BULLET_SIZE {
+        if self.swept_collider().intersects(&player.collider()) {
             Some(DeathReason::HitPlayer)
         }
         else{ None }
@@ -214,53 +259,353 @@ pub struct Player{
     pub base: Entity,
     pub score: u32,
     pub kills: u32,
-    pub power: u32,
+    /// index of the selected weapon in WEAPONS, which receives power from items
+    pub weapon: usize,
+    /// power collected for each weapon in WEAPONS
+    pub weapon_power: Vec<u32>,
     pub lives: u32,
+    /// score to reach for the next extra life
+    pub next_extend: u32,
+    /// remaining frames to show the extra life notification
+    pub extend_time: u32,
     /// invincibility time caused by death or bomb
     pub invtime: u32,
+    /// remaining frames of the explosion after death, the ship is gone meanwhile
+    pub dead_time: u32,
+    /// true while the ship is flying back in from the bottom after respawn
+    pub entering: bool,
     pub cooldown: u32,
+    /// slow movement and hitbox display while the focus key is held
+    pub focus: bool,
+    /// number of projectiles grazed
+    pub graze: u32,
+    /// number of kills chained without letting combo_time run out
+    pub combo: u32,
+    pub combo_time: u32,
+    /// bomb stock, refilled on each life
+    pub bombs: u32,
+    /// remaining frames of the active bomb effect
+    pub bomb_time: u32,
+    /// remaining frames of the shield from the item, absorbing hits
+    pub shield_time: u32,
+    /// number of medals collected in a row, which raises their value
+    pub medal_chain: u32,
+    /// energy drained by continuous weapons while firing
+    pub energy: u32,
+    /// true after energy ran out, continuous weapons can't fire until it's full again
+    pub overheat: bool,
+    /// frames the fire key has been held with a weapon that can charge
+    pub charge: u32,
+    /// positions the ship moved through, latest first, followed by the options
+    pub trail: Vec<[f64; 2]>,
+    /// current positions of the option drones
+    pub options: Vec<[f64; 2]>,
+    /// locks of the lock-on weapon, whose targets and damage are kept in Reservations
+    pub locks: Vec<u32>,
 }
 
+/// Offsets of the options from the ship in formation mode, used while focused
+const OPTION_FORMATION: [[f64; 2]; OPTION_MAX] = [[-20., -8.], [20., -8.], [-36., 4.], [36., 4.]];
+
 impl Player{
     pub fn new(base: Entity) -> Self{
-        Self{base, score: 0, kills: 0, power: 0, lives: PLAYER_LIVES, invtime: 0, cooldown: 0}
+        let base = base.shape(Shape::Circle(PLAYER_HIT_RADIUS));
+        Self{base, score: 0, kills: 0, weapon: 0, weapon_power: vec![0; WEAPONS.len()], lives: PLAYER_LIVES,
+            next_extend: EXTEND_SCORE_INTERVAL, extend_time: 0, invtime: 0, dead_time: 0, entering: false, cooldown: 0, focus: false,
+            graze: 0, combo: 0, combo_time: 0, bombs: PLAYER_BOMBS, bomb_time: 0,
+            shield_time: 0, medal_chain: 0, energy: WEAPON_ENERGY_MAX, overheat: false, charge: 0,
+            trail: vec![], options: vec![], locks: vec![]}
+    }
+
+    pub fn speed(&self) -> f64{
+        if self.focus { PLAYER_FOCUS_SPEED } else { PLAYER_SPEED }
     }
 
     pub fn move_up(&mut self){
-        if PLAYER_SIZE <= self.base.pos[1] - PLAYER_SPEED {
-            self.base.pos[1] -= PLAYER_SPEED;
+        let speed = self.speed();
+        if PLAYER_SIZE <= self.base.pos[1] - speed {
+            self.base.pos[1] -= speed;
         }
     }
 
     pub fn move_down(&mut self){
-        if self.base.pos[1] + PLAYER_SPEED < HEIGHT as f64 - PLAYER_SIZE {
-            self.base.pos[1] += PLAYER_SPEED;
+        let speed = self.speed();
+        if self.base.pos[1] + speed < HEIGHT as f64 - PLAYER_SIZE {
+            self.base.pos[1] += speed;
         }
     }
 
     pub fn move_left(&mut self){
-        if PLAYER_SIZE <= self.base.pos[0] - PLAYER_SPEED {
-            self.base.pos[0] -= PLAYER_SPEED;
+        let speed = self.speed();
+        if PLAYER_SIZE <= self.base.pos[0] - speed {
+            self.base.pos[0] -= speed;
         }
     }
 
     pub fn move_right(&mut self){
-        if self.base.pos[0] + PLAYER_SPEED < WIDTH as f64 - PLAYER_SIZE {
-            self.base.pos[0] += PLAYER_SPEED;
+        let speed = self.speed();
+        if self.base.pos[0] + speed < WIDTH as f64 - PLAYER_SIZE {
+            self.base.pos[0] += speed;
         }
     }
 
     pub fn reset(&mut self){
-        self.base.pos = [240., 400.];
+        self.base.pos = PLAYER_SPAWN_POS;
         self.score = 0;
         self.kills = 0;
-        self.power = 0;
+        self.weapon = 0;
+        self.weapon_power = vec![0; WEAPONS.len()];
         self.lives = PLAYER_LIVES;
+        self.next_extend = EXTEND_SCORE_INTERVAL;
+        self.extend_time = 0;
         self.invtime = 0;
+        self.dead_time = 0;
+        self.entering = false;
+        self.focus = false;
+        self.graze = 0;
+        self.break_combo();
+        self.bombs = PLAYER_BOMBS;
+        self.bomb_time = 0;
+        self.shield_time = 0;
+        self.medal_chain = 0;
+        self.energy = WEAPON_ENERGY_MAX;
+        self.overheat = false;
+        self.charge = 0;
+        self.trail.clear();
+        self.options.clear();
+        self.locks.clear();
+    }
+
+    pub fn is_alive(&self) -> bool{
+        self.dead_time == 0
+    }
+
+    /// Returns true if an enemy projectile would kill the player.
+    pub fn is_vulnerable(&self) -> bool{
+        self.is_alive() && self.invtime == 0 && self.shield_time == 0
+    }
+
+    /// Score of the last collected medal in the chain.
+    pub fn medal_score(&self) -> u32{
+        MEDAL_SCORE * std::cmp::min(std::cmp::max(self.medal_chain, 1), MEDAL_MAX_CHAIN)
+    }
+
+    /// The player cannot move or shoot while dead or entering the screen.
+    pub fn can_control(&self) -> bool{
+        self.is_alive() && !self.entering
+    }
+
+    /// Loses a life and starts the death sequence.  The ship is moved out of the
+    /// screen so that nothing collides with the wreck.
+    /// Returns the amount of power lost by the selected weapon, which should be
+    /// scattered as items.  Picking them up gives it back to the same weapon
+    /// unless the player switches in the meantime.
+    pub fn kill(&mut self) -> u32{
+        self.base.pos = [PLAYER_SPAWN_POS[0], HEIGHT as f64 + PLAYER_SIZE];
+        self.lives = self.lives.saturating_sub(1);
+        self.dead_time = PLAYER_DEATH_TIME;
+        self.bomb_time = 0;
+        self.shield_time = 0;
+        self.medal_chain = 0;
+        self.charge = 0;
+        self.trail.clear();
+        self.options.clear();
+        self.break_combo();
+        let power = &mut self.weapon_power[self.weapon];
+        let lost = *power * DEATH_POWER_LOSS_PERCENT / 100;
+        *power -= lost;
+        lost
+    }
+
+    /// Advances the death sequence.  Returns true at the frame the ship should
+    /// respawn, which is never after the last life is lost.
+    pub fn update_death(&mut self) -> bool{
+        if self.dead_time == 0 {
+            return false;
+        }
+        self.dead_time -= 1;
+        if self.dead_time == 0 && 0 < self.lives {
+            self.entering = true;
+            self.invtime = PLAYER_INVINCIBLE_TIME;
+            self.bombs = PLAYER_BOMBS;
+            self.energy = WEAPON_ENERGY_MAX;
+            self.overheat = false;
+            return true;
+        }
+        false
+    }
+
+    /// Number of options granted by the power level of the selected weapon.
+    pub fn option_count(&self) -> usize{
+        std::cmp::min((self.power_level() / OPTION_LEVELS) as usize, OPTION_MAX)
+    }
+
+    /// Where the i-th option is heading: fixed formation around the ship while
+    /// focused, otherwise trailing behind along the path the ship took.
+    pub fn option_target(&self, i: usize) -> [f64; 2]{
+        if self.focus {
+            vec2_add(self.base.pos, OPTION_FORMATION[i])
+        }
+        else {
+            let index = (i + 1) * OPTION_TRAIL_SPACING;
+            *self.trail.get(index).or(self.trail.last()).unwrap_or(&self.base.pos)
+        }
+    }
+
+    /// Records the ship position and moves the options toward their targets.
+    pub fn update_options(&mut self){
+        if !self.is_alive() {
+            return;
+        }
+        // Only record movement so that the options stack up when the ship stops
+        if self.trail.first() != Some(&self.base.pos) {
+            self.trail.insert(0, self.base.pos);
+            self.trail.truncate(OPTION_MAX * OPTION_TRAIL_SPACING + 1);
+        }
+        let count = self.option_count();
+        let pos = self.base.pos;
+        self.options.resize(count, pos);
+        for i in 0..count {
+            let target = self.option_target(i);
+            let option = &mut self.options[i];
+            *option = vec2_add(*option, vec2_scale(vec2_sub(target, *option), OPTION_FOLLOW_RATE));
+        }
+    }
+
+    /// Flies the ship up to the spawn position after respawn.
+    pub fn update_entry(&mut self){
+        if !self.entering {
+            return;
+        }
+        self.base.pos[1] -= PLAYER_SPEED;
+        if self.base.pos[1] <= PLAYER_SPAWN_POS[1] {
+            self.base.pos[1] = PLAYER_SPAWN_POS[1];
+            self.entering = false;
+        }
+    }
+
+    /// Adds a life up to the cap.  Returns true if a life was added.
+    pub fn extend(&mut self) -> bool{
+        if PLAYER_LIVES_MAX <= self.lives {
+            return false;
+        }
+        self.lives += 1;
+        self.extend_time = EXTEND_NOTIFY_TIME;
+        true
+    }
+
+    /// Awards extra lives for every score threshold passed since the last call.
+    pub fn check_extend(&mut self){
+        while self.next_extend <= self.score {
+            self.next_extend += EXTEND_SCORE_INTERVAL;
+            self.extend();
+        }
+    }
+
+    pub fn multiplier(&self) -> u32{
+        std::cmp::min(1 + self.combo / COMBO_STEP, COMBO_MAX_MULTIPLIER)
+    }
+
+    /// Chains a kill and adds its score multiplied by the current combo.
+    /// Returns the awarded score.
+    pub fn add_kill(&mut self, score: u32) -> u32{
+        self.kills += 1;
+        self.combo += 1;
+        self.combo_time = COMBO_WINDOW;
+        let awarded = score * self.multiplier();
+        self.score += awarded;
+        awarded
+    }
+
+    /// Counts down the combo window.  When it runs out the combo decays by one
+    /// multiplier step at a time rather than being lost at once.
+    pub fn update_combo(&mut self){
+        if self.combo_time == 0 {
+            return;
+        }
+        self.combo_time -= 1;
+        if self.combo_time == 0 {
+            self.combo = self.combo.saturating_sub(COMBO_STEP);
+            if 0 < self.combo {
+                self.combo_time = COMBO_WINDOW / 2;
+            }
+        }
+    }
+
+    pub fn break_combo(&mut self){
+        self.combo = 0;
+        self.combo_time = 0;
+    }
+
+    /// Starts a bomb if there is stock and no bomb is active.
+    /// Returns true if a bomb was used.
+    pub fn use_bomb(&mut self) -> bool{
+        if self.bombs == 0 || 0 < self.bomb_time {
+            return false;
+        }
+        self.bombs -= 1;
+        self.bomb_time = BOMB_TIME;
+        self.invtime = std::cmp::max(self.invtime, BOMB_INVINCIBLE_TIME);
+        true
+    }
+
+    pub fn get_weapon(&self) -> &'static dyn Weapon{
+        WEAPONS[self.weapon]
+    }
+
+    /// Power of the selected weapon.
+    pub fn power(&self) -> u32{
+        self.weapon_power[self.weapon]
+    }
+
+    /// Adds power to the selected weapon up to its cap.
+    pub fn add_power(&mut self, amount: u32){
+        let max_power = self.get_weapon().max_power();
+        let power = &mut self.weapon_power[self.weapon];
+        *power = std::cmp::min(*power + amount, max_power);
+    }
+
+    pub fn weapon_level(&self, weapon: usize) -> u32{
+        std::cmp::min(self.weapon_power[weapon] / WEAPON_POWER_PER_LEVEL, WEAPONS[weapon].max_level())
     }
 
     pub fn power_level(&self) -> u32{
-        self.power >> 4
+        self.weapon_level(self.weapon)
+    }
+
+    /// Consumes energy for a frame of fire.  Returns false if the weapon can't
+    /// fire because of overheat.  Weapons without cost always fire.
+    pub fn drain_energy(&mut self, cost: u32) -> bool{
+        if cost == 0 {
+            return true;
+        }
+        if self.overheat {
+            return false;
+        }
+        self.energy = self.energy.saturating_sub(cost);
+        if self.energy == 0 {
+            self.overheat = true;
+        }
+        true
+    }
+
+    /// Recharges energy for a frame without continuous fire.
+    pub fn recharge_energy(&mut self){
+        self.energy = std::cmp::min(self.energy + WEAPON_ENERGY_RECHARGE, WEAPON_ENERGY_MAX);
+        if self.energy == WEAPON_ENERGY_MAX {
+            self.overheat = false;
+        }
+    }
+
+    /// True while a charge shot is ready to be released, which stops auto-fire.
+    pub fn is_charged(&self) -> bool{
+        CHARGE_THRESHOLD <= self.charge
+    }
+
+    /// Selects the next weapon in WEAPONS, or the previous one if `forward` is false.
+    pub fn switch_weapon(&mut self, forward: bool){
+        self.weapon = (self.weapon + if forward { 1 } else { WEAPONS.len() - 1 }) % WEAPONS.len();
+        self.charge = 0;
     }
 
     pub fn difficulty_level(&self) -> u32{
@@ -273,25 +618,568 @@ fn test_player_hit() {
     let mut id_gen: u32 = 0;
 
     // Tests for killed
-    let ent = Entity::new(&mut id_gen, [10., 20.], [1., 2., ]).health(0);
+    let radius = 4.;
+    let ent = Entity::new(&mut id_gen, [10., 20.], [0., 0.]).health(0).shape(Shape::Circle(radius));
 
-    let mut player = Player::new(Entity::new(&mut id_gen, [10. + ENEMY_SIZE + 0.5, 20.], [0., 1.]));
+    let mut player = Player::new(Entity::new(&mut id_gen, [10. + radius + PLAYER_HIT_RADIUS - 0.5, 20.], [0., 1.]));
     assert!(if let Some(DeathReason::HitPlayer) = ent.hits_player(&player.base) { true } else { false });
 
-    player.base.pos[0] += BULLET_SIZE;
+    player.base.pos[0] += 1.;
+    assert!(if let None = ent.hits_player(&player.base) { true } else { false });
+
+    // Grazing the sprite diagonally must not count as a hit
+    player.base.pos = [10. + ENEMY_SIZE, 20. + ENEMY_SIZE];
     assert!(if let None = ent.hits_player(&player.base) { true } else { false });
+
+    // A fast projectile hits the core lying between this frame and the next
+    player.base.pos = [10., 20. + 15.];
+    let fast = Entity::new(&mut id_gen, [10., 20.], [0., 30.]).shape(Shape::Circle(radius));
+    assert!(if let Some(DeathReason::HitPlayer) = fast.hits_player(&player.base) { true } else { false });
+}
+
+#[test]
+fn test_graze() {
+    let mut id_gen: u32 = 0;
+    let player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    // Passing by the player counts only once
+    let mut bullet = Projectile::new_enemy_bullet(BulletBase::new(Entity::new(&mut id_gen, [100. + PLAYER_GRAZE_RADIUS, 100.], [0., 1.])));
+    assert!(bullet.get_base().ent.hits_player(&player.base).is_none());
+    assert!(bullet.test_graze(&player.base));
+    assert!(!bullet.test_graze(&player.base));
+
+    // Too far away to graze
+    let mut bullet = Projectile::new_enemy_bullet(BulletBase::new(Entity::new(&mut id_gen, [100. + PLAYER_GRAZE_RADIUS + 5., 100.], [0., 1.])));
+    assert!(!bullet.test_graze(&player.base));
+
+    // Player's own bullets never graze
+    let mut bullet = Projectile::new_bullet(BulletBase::new(Entity::new(&mut id_gen, [100., 100.], [0., -1.])));
+    assert!(!bullet.test_graze(&player.base));
+}
+
+#[test]
+fn test_bullet_tunneling() {
+    let mut id_gen: u32 = 0;
+    let mut enemies = vec![Enemy::new_enemy1(&mut id_gen, [100., 100.], [0., 0.])];
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 400.], [0., 0.]));
+
+    // The bullet is behind the enemy in this frame and past it in the next
+    let speed = (ENEMY_SIZE + 4.) * 2. + 10.;
+    let mut bullet = Projectile::new_bullet(BulletBase::new(Entity::new(&mut id_gen, [100., 100. + speed / 2.], [0., -speed])));
+    assert!(!enemies[0].hits(&bullet.get_base().ent.collider()));
+    assert!(if let Some(DeathReason::Killed) = bullet.animate_bullet(&mut enemies, &mut player.base) { true } else { false });
+    assert!(enemies[0].get_base().health < 3);
+}
+
+#[test]
+fn test_projectile_shape() {
+    let mut id_gen: u32 = 0;
+    let player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    // An elongated bullet hits along its long axis but misses at the same distance
+    // along its short axis.
+    let offset = 7.;
+    let phase = Projectile::new_phase(BulletBase::new(Entity::new(&mut id_gen, [100. + offset, 100.], [1., 0.])));
+    assert!(phase.get_base().ent.hits_player(&player.base).is_some());
+    let phase = Projectile::new_phase(BulletBase::new(Entity::new(&mut id_gen, [100., 100. + offset], [1., 0.])));
+    assert!(phase.get_base().ent.hits_player(&player.base).is_none());
+
+    // Rotating the bullet swaps the axes
+    let spiral = Projectile::new_spiral(BulletBase::new(Entity::new(&mut id_gen, [100., 100. + offset], [0., 1.])
+        .rotation(std::f32::consts::FRAC_PI_2)));
+    assert!(spiral.get_base().ent.hits_player(&player.base).is_some());
+    let spiral = Projectile::new_spiral(BulletBase::new(Entity::new(&mut id_gen, [100. + offset, 100.], [0., 1.])
+        .rotation(std::f32::consts::FRAC_PI_2)));
+    assert!(spiral.get_base().ent.hits_player(&player.base).is_none());
+}
+
+#[test]
+fn test_enemy_shape() {
+    let mut id_gen: u32 = 0;
+    let bullet = Projectile::new_bullet(BulletBase::new(Entity::new(&mut id_gen, [100., 100.], [0., -BULLET_SPEED])));
+    let bullet_collider = bullet.get_base().ent.collider();
+
+    // Round enemies are missed near the corner of their bounding square
+    let enemy = Enemy::new_enemy1(&mut id_gen, [100. + ENEMY_SIZE + 2., 100. + ENEMY_SIZE + 5.], [0., 0.]);
+    assert!(!enemy.hits(&bullet_collider));
+    let enemy = Enemy::new_enemy1(&mut id_gen, [100. + ENEMY_SIZE + 2., 100.], [0., 0.]);
+    assert!(enemy.hits(&bullet_collider));
+
+    // The shield of ShieldedBoss extends its collision circle
+    let boss = ShieldedBoss::new(&mut id_gen, [100. + 40., 100.], [0., 0.]);
+    assert!(Enemy::ShieldedBoss(boss).hits(&bullet_collider));
+    let mut boss = ShieldedBoss::new(&mut id_gen, [100. + 40., 100.], [0., 0.]);
+    boss.shield_health = 0;
+    assert!(!Enemy::ShieldedBoss(boss).hits(&bullet_collider));
+}
+
+#[test]
+fn test_item_pickup() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    // Items are collected with the whole ship, not just the hit core
+    let mut item = Item::new(ItemKind::PowerUp, Entity::new(&mut id_gen, [100. + PLAYER_PICKUP_RADIUS, 100.], [0., 0.]));
+    assert!(if let Some(DeathReason::Killed) = item.animate(&mut player) { true } else { false });
+    assert_eq!(player.power(), 1);
+
+    let mut item = Item::new(ItemKind::PowerUp, Entity::new(&mut id_gen, [100. + PLAYER_PICKUP_RADIUS + ENEMY_SIZE, 100.], [0., 0.]));
+    assert!(if let None = item.animate(&mut player) { true } else { false });
+    assert_eq!(player.power(), 1);
+
+    // Bomb items add to the stock up to the limit
+    player.bombs = PLAYER_BOMBS_MAX - 1;
+    for _ in 0..2 {
+        let mut item = Item::Bomb(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+        assert!(if let Some(DeathReason::Killed) = item.animate(&mut player) { true } else { false });
+    }
+    assert_eq!(player.bombs, PLAYER_BOMBS_MAX);
+    assert_eq!(player.power(), 1);
+}
+
+#[test]
+fn test_death_sequence() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+    player.add_kill(1);
+
+    player.add_power(35);
+    player.switch_weapon(true);
+    player.add_power(20);
+    // Only the selected weapon loses power
+    let lost = 20 * DEATH_POWER_LOSS_PERCENT / 100;
+    assert_eq!(player.kill(), lost);
+    assert_eq!(player.power(), 20 - lost);
+    assert_eq!(player.weapon_power[0], 35);
+    assert_eq!(player.lives, PLAYER_LIVES - 1);
+    assert_eq!(player.combo, 0);
+    assert!(!player.is_alive());
+    assert!(!player.can_control());
+
+    // Dead ships do not collect items, even one right where the ship is
+    let power = player.power();
+    let mut item = Item::PowerUp(Entity::new(&mut id_gen, player.base.pos, [0., 0.]));
+    assert!(if let Some(DeathReason::Killed) = item.animate(&mut player) { false } else { true });
+    assert_eq!(player.power(), power);
+
+    for _ in 0..PLAYER_DEATH_TIME - 1 {
+        assert!(!player.update_death());
+    }
+    assert!(player.update_death());
+    assert!(player.is_alive());
+    assert!(!player.can_control());
+    assert_eq!(player.invtime, PLAYER_INVINCIBLE_TIME);
+    assert!(PLAYER_SPAWN_POS[1] < player.base.pos[1]);
+
+    // Flies in from the bottom and regains control at the spawn position
+    for _ in 0..1000 {
+        player.update_entry();
+    }
+    assert!(player.can_control());
+    assert_eq!(player.base.pos, PLAYER_SPAWN_POS);
+
+    // No respawn after the last life
+    player.lives = 1;
+    player.kill();
+    for _ in 0..PLAYER_DEATH_TIME {
+        assert!(!player.update_death());
+    }
+    assert!(player.is_alive());
+    assert_eq!(player.lives, 0);
 }
 
+#[test]
+fn test_power_scatter() {
+    let mut id_gen: u32 = 0;
+    let items = Item::scatter(&mut id_gen, [100., 200.], 34);
+    assert_eq!(items.len(), 3 + 4);
+    assert_eq!(items.iter().map(|item| item.power_value()).sum::<u32>(), 34);
+    // All items are thrown upward so that they come back down around the death point
+    assert!(items.iter().all(|item| item.get_base().velo[1] < 0.));
+    assert!(items.first().unwrap().get_base().velo[0] > 0.);
+    assert!(items.last().unwrap().get_base().velo[0] < 0.);
+
+    // Number of items is capped
+    let items = Item::scatter(&mut id_gen, [100., 200.], 1000);
+    assert_eq!(items.len(), DEATH_SCATTER_MAX_ITEMS);
+
+    assert!(Item::scatter(&mut id_gen, [100., 200.], 0).is_empty());
+
+    // Nothing is lost off the screen after a death in a corner
+    let mut player = Player::new(Entity::new(&mut id_gen, [400., 400.], [0., 0.]));
+    for &pos in &[[0., 0.], [WIDTH as f64, 0.]] {
+        for mut item in Item::scatter(&mut id_gen, pos, 34) {
+            for _ in 0..200 {
+                assert!(item.animate(&mut player).is_none());
+            }
+        }
+    }
+
+    // Falls down after reaching the top of the arc
+    let mut player = Player::new(Entity::new(&mut id_gen, [400., 400.], [0., 0.]));
+    let mut item = Item::scatter(&mut id_gen, [100., 200.], 1).pop().unwrap();
+    for _ in 0..100 {
+        assert!(item.animate(&mut player).is_none());
+    }
+    assert_eq!(item.get_base().velo[1], ITEM_SPEED);
+}
+
+#[test]
+fn test_extend() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    player.score = EXTEND_SCORE_INTERVAL - 1;
+    player.check_extend();
+    assert_eq!(player.lives, PLAYER_LIVES);
+    assert_eq!(player.extend_time, 0);
+
+    // Passing two thresholds at once gives two lives
+    player.score = EXTEND_SCORE_INTERVAL * 2;
+    player.check_extend();
+    assert_eq!(player.lives, PLAYER_LIVES + 2);
+    assert_eq!(player.next_extend, EXTEND_SCORE_INTERVAL * 3);
+    assert_eq!(player.extend_time, EXTEND_NOTIFY_TIME);
+
+    // 1-up items never exceed the cap
+    for _ in 0..PLAYER_LIVES_MAX {
+        let mut item = Item::OneUp(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+        assert!(if let Some(DeathReason::Killed) = item.animate(&mut player) { true } else { false });
+    }
+    assert_eq!(player.lives, PLAYER_LIVES_MAX);
+
+    player.reset();
+    assert_eq!(player.next_extend, EXTEND_SCORE_INTERVAL);
+}
+
+#[test]
+fn test_combo() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    for _ in 0..COMBO_STEP - 1 {
+        assert_eq!(player.add_kill(1), 1);
+    }
+    assert_eq!(player.add_kill(BOSS_SCORE), BOSS_SCORE * 2);
+    assert_eq!(player.multiplier(), 2);
+    assert_eq!(player.score, COMBO_STEP - 1 + BOSS_SCORE * 2);
+    assert_eq!(player.kills, COMBO_STEP);
+
+    // Stopping to kill drops one multiplier step at a time
+    for _ in 0..COMBO_WINDOW {
+        player.update_combo();
+    }
+    assert_eq!(player.combo, 0);
+    assert_eq!(player.multiplier(), 1);
+
+    for _ in 0..COMBO_STEP * 2 {
+        player.add_kill(1);
+    }
+    assert_eq!(player.multiplier(), 3);
+    for _ in 0..COMBO_WINDOW {
+        player.update_combo();
+    }
+    assert_eq!(player.multiplier(), 2);
+    assert_eq!(player.combo_time, COMBO_WINDOW / 2);
+
+    // Multiplier is capped
+    for _ in 0..COMBO_STEP * COMBO_MAX_MULTIPLIER {
+        player.add_kill(1);
+    }
+    assert_eq!(player.multiplier(), COMBO_MAX_MULTIPLIER);
+
+    player.break_combo();
+    assert_eq!(player.multiplier(), 1);
+}
+
+#[test]
+fn test_weapon_power() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    // Power goes to the selected weapon only
+    player.add_power(WEAPON_POWER_PER_LEVEL * 2);
+    assert_eq!(player.power_level(), 2);
+    player.switch_weapon(false);
+    assert_eq!(player.weapon, WEAPONS.len() - 1);
+    assert_eq!(player.power_level(), 0);
+    assert_eq!(player.weapon_level(0), 2);
+
+    // Each track is capped at its own maximum level
+    player.add_power(1000);
+    assert_eq!(player.power(), player.get_weapon().max_power());
+    assert_eq!(player.power_level(), player.get_weapon().max_level());
+
+    player.switch_weapon(true);
+    player.reset();
+    assert_eq!(player.weapon, 0);
+    assert!(player.weapon_power.iter().all(|&power| power == 0));
+}
+
+#[test]
+fn test_energy() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+
+    assert!(player.drain_energy(0));
+    assert_eq!(player.energy, WEAPON_ENERGY_MAX);
+
+    let cost = 3;
+    let mut frames = 0;
+    while player.drain_energy(cost) {
+        frames += 1;
+    }
+    assert_eq!(frames, (WEAPON_ENERGY_MAX + cost - 1) / cost);
+    assert!(player.overheat);
+
+    // Locked out until fully recharged, but free weapons still fire
+    assert!(player.drain_energy(0));
+    player.recharge_energy();
+    assert!(!player.drain_energy(cost));
+    while player.energy < WEAPON_ENERGY_MAX {
+        assert!(player.overheat);
+        player.recharge_energy();
+    }
+    assert!(!player.overheat);
+    assert!(player.drain_energy(cost));
+}
+
+#[test]
+fn test_options() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 300.], [0., 0.]));
+
+    player.update_options();
+    assert!(player.options.is_empty());
+    player.add_power(WEAPON_POWER_PER_LEVEL * OPTION_LEVELS * 2);
+    assert_eq!(player.option_count(), 2);
+    player.add_power(10000);
+    assert_eq!(player.option_count(), std::cmp::min((player.power_level() / OPTION_LEVELS) as usize, OPTION_MAX));
+
+    // Trailing options follow the path the ship took
+    for _ in 0..OPTION_MAX * OPTION_TRAIL_SPACING * 2 {
+        player.move_right();
+        player.update_options();
+    }
+    for _ in 0..100 {
+        player.update_options();
+    }
+    assert_eq!(player.options.len(), player.option_count());
+    for (i, option) in player.options.iter().enumerate() {
+        let expected = player.base.pos[0] - PLAYER_SPEED * ((i + 1) * OPTION_TRAIL_SPACING) as f64;
+        assert!((option[0] - expected).abs() < 1e-3);
+        assert_eq!(option[1], 300.);
+    }
+
+    // Formation mode keeps them around the ship
+    player.focus = true;
+    for _ in 0..100 {
+        player.update_options();
+    }
+    for (i, option) in player.options.iter().enumerate() {
+        assert!(vec2_len(vec2_sub(*option, vec2_add(player.base.pos, OPTION_FORMATION[i]))) < 1e-3);
+    }
+
+    player.kill();
+    assert!(player.options.is_empty());
+}
+
+#[test]
+fn test_item_magnet() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 300.], [0., 0.]));
+    player.entering = false;
+
+    // An item out of the magnet radius just falls
+    let mut item = Item::new(ItemKind::PowerUp, Entity::new(&mut id_gen, [100. + ITEM_MAGNET_RADIUS + 1., 300.], [0., 0.]));
+    assert!(!item.is_attracted(&player));
+    item.animate(&mut player);
+    assert_eq!(item.get_base().velo[0], 0.);
+
+    // An item inside it is pulled toward the player
+    let mut item = Item::new(ItemKind::PowerUp, Entity::new(&mut id_gen, [100. + ITEM_MAGNET_RADIUS - 1., 300.], [0., 0.]));
+    assert!(item.is_attracted(&player));
+    item.animate(&mut player);
+    assert!(item.get_base().velo[0] < 0.);
+
+    // Above the auto-collect line, every item on screen homes in and gets collected
+    player.base.pos = [100., ITEM_COLLECT_LINE - 1.];
+    let mut item = Item::new(ItemKind::PowerUp, Entity::new(&mut id_gen, [400., 400.], [0., 0.]));
+    assert!(item.is_attracted(&player));
+    let collected = (0..200).any(|_| if let Some(DeathReason::Killed) = item.animate(&mut player) { true } else { false });
+    assert!(collected);
+    assert_eq!(player.power(), 1);
+
+    // Dead players don't attract items
+    player.kill();
+    assert!(!item.is_attracted(&player));
+}
+
+#[test]
+fn test_drop_table() {
+    for table in &[ENEMY1_DROPS, BOSS_DROPS, SHIELDED_BOSS_DROPS, SPIRAL_ENEMY_DROPS] {
+        let total = table.total_weight();
+        assert!(0 < total);
+        assert!(table.pick(total - 1).is_some());
+        assert!(table.pick(total).is_none());
+    }
+    assert_eq!(BOSS_DROPS.pick(0), Some(ItemKind::PowerUp10));
+    assert_eq!(BOSS_DROPS.pick(60), Some(ItemKind::Medal));
+    assert_eq!(BOSS_DROPS.pick(BOSS_DROPS.total_weight() - 1), Some(ItemKind::FullPower));
+
+    let never = DropTable{rate: 0, items: &[(ItemKind::PowerUp, 1)]};
+    let always = DropTable{rate: 100, items: &[(ItemKind::Medal, 1)]};
+    let mut rng = thread_rng();
+    for _ in 0..10 {
+        assert_eq!(never.roll(&mut rng), None);
+        assert_eq!(always.roll(&mut rng), Some(ItemKind::Medal));
+    }
+}
+
+#[test]
+fn test_item_effects() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 300.], [0., 0.]));
+    let mut collect = |kind, player: &mut Player| {
+        let mut item = Item::new(kind, Entity::new(&mut id_gen, [100., 300.], [0., 0.]));
+        assert!(if let Some(DeathReason::Killed) = item.animate(player) { true } else { false });
+        item
+    };
+
+    collect(ItemKind::Shield, &mut player);
+    assert_eq!(player.shield_time, PLAYER_SHIELD_TIME);
+    assert!(!player.is_vulnerable());
+
+    collect(ItemKind::FullPower, &mut player);
+    assert_eq!(player.power(), player.get_weapon().max_power());
+    assert_eq!(player.power_level(), player.get_weapon().max_level());
+
+    // Medals are worth more when collected in a row
+    for i in 1..MEDAL_MAX_CHAIN + 2 {
+        let score = player.score;
+        let item = collect(ItemKind::Medal, &mut player);
+        let value = MEDAL_SCORE * std::cmp::min(i, MEDAL_MAX_CHAIN);
+        assert_eq!(player.score, score + value);
+        assert_eq!(item.pickup_text(&player), Some(format!("{}", value)));
+    }
+
+    // Missing one resets the chain
+    let mut medal = Item::new(ItemKind::Medal, Entity::new(&mut id_gen, [10., HEIGHT as f64], [0., 1.]));
+    assert!(if let Some(DeathReason::RangeOut) = medal.animate(&mut player) { true } else { false });
+    assert_eq!(player.medal_chain, 0);
+}
+
+#[test]
+fn test_bomb() {
+    let mut id_gen: u32 = 0;
+    let mut player = Player::new(Entity::new(&mut id_gen, [100., 100.], [0., 0.]));
+    assert!(player.use_bomb());
+    assert_eq!(player.bombs, PLAYER_BOMBS - 1);
+    assert_eq!(player.invtime, BOMB_INVINCIBLE_TIME);
+
+    // Cannot use another bomb while one is active
+    assert!(!player.use_bomb());
+    player.bomb_time = 0;
+    player.bombs = 0;
+    assert!(!player.use_bomb());
+
+    // Bombs damage through the shield
+    let mut boss = Enemy::ShieldedBoss(ShieldedBoss::new(&mut id_gen, [0., 0.], [0., 0.]));
+    let health = boss.get_base().health;
+    boss.bomb_damage(BOMB_DAMAGE);
+    assert_eq!(boss.get_base().health, health - BOMB_DAMAGE);
+    assert!(if let Enemy::ShieldedBoss(boss) = boss { boss.shield_health == 64 - BOMB_DAMAGE } else { false });
+}
+
+
+/// How an enemy aims a volley of shots.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShotPattern{
+    /// Evenly spread in all directions, ignoring the player
+    Ring(u32),
+    /// Straight at the player
+    Aimed,
+    /// Fan of shots ENEMY_NWAY_SPREAD apart, centered on the player
+    NWay(u32),
+    /// At where the player will be if it keeps moving at the same velocity
+    Lead,
+}
+
+impl ShotPattern{
+    /// Directions in radians of the shots fired from `pos` at `speed`.  Shots
+    /// aimed at the player go straight down if there is no `player` to aim at.
+    pub fn angles(&self, pos: [f64; 2], speed: f64, player: Option<&Entity>, rng: &mut rand::rngs::ThreadRng) -> Vec<f64>{
+        use std::f64::consts::PI;
+        let aim = |target: [f64; 2]| {
+            let delta = vec2_sub(target, pos);
+            delta[1].atan2(delta[0])
+        };
+        let down = [pos[0], pos[1] + 1.];
+        match *self {
+            ShotPattern::Ring(count) => {
+                let phase_offset = rng.gen::<f64>() * PI;
+                (0..count).map(|i| 2. * PI * i as f64 / count as f64 + phase_offset).collect()
+            }
+            ShotPattern::Aimed => vec![aim(player.map_or(down, |p| p.pos))],
+            ShotPattern::NWay(count) => {
+                let center = aim(player.map_or(down, |p| p.pos));
+                (0..count).map(|i| center + (i as f64 - (count - 1) as f64 / 2.) * ENEMY_NWAY_SPREAD).collect()
+            }
+            ShotPattern::Lead => vec![aim(player.map_or(down, |p| lead_target(pos, speed, p)))],
+        }
+    }
+}
+
+/// Speed of enemy shots, getting faster with the difficulty level.
+pub fn enemy_shot_speed(level: u32) -> f64{
+    (ENEMY_SHOT_SPEED + level as f64 * ENEMY_SHOT_SPEED_PER_LEVEL).min(ENEMY_SHOT_SPEED_MAX)
+}
+
+/// Predicts where a shot from `pos` at `speed` meets the player, assuming the
+/// player keeps its velocity.  Falls back to the current position if the shot
+/// can't catch up.
+pub fn lead_target(pos: [f64; 2], speed: f64, player: &Entity) -> [f64; 2]{
+    // Solve |delta + velo * t| = speed * t for the earliest positive t
+    let delta = vec2_sub(player.pos, pos);
+    let velo = player.velo;
+    let a = vec2_dot(velo, velo) - speed * speed;
+    let b = 2. * vec2_dot(delta, velo);
+    let c = vec2_dot(delta, delta);
+    let t = if a.abs() < std::f64::EPSILON {
+        if b < 0. { -c / b } else { 0. }
+    }
+    else{
+        let disc = b * b - 4. * a * c;
+        if disc < 0. {
+            0.
+        }
+        else{
+            let sq = disc.sqrt();
+            let (t1, t2) = ((-b - sq) / (2. * a), (-b + sq) / (2. * a));
+            let (t1, t2) = (t1.min(t2), t1.max(t2));
+            if 0. < t1 { t1 } else if 0. < t2 { t2 } else { 0. }
+        }
+    };
+    vec2_add(player.pos, vec2_scale(velo, t))
+}
 
-pub struct EnemyBase(Entity, i32);
+pub struct EnemyBase{
+    ent: Entity,
+    movement: Movement,
+}
 
 impl EnemyBase{
     pub fn new(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Self{
-        Self(Entity::new(id_gen, pos, velo).health(64), 0)
+        Self{ent: Entity::new(id_gen, pos, velo).health(64), movement: Movement::default()}
     }
 
     pub fn health(mut self, val: i32) -> Self {
-        self.0 = self.0.health(val);
+        self.ent = self.ent.health(val);
+        self
+    }
+
+    pub fn shape(mut self, shape: Shape) -> Self {
+        self.ent = self.ent.shape(shape);
         self
     }
 }
@@ -303,7 +1191,58 @@ pub struct ShieldedBoss{
 
 impl ShieldedBoss{
     pub fn new(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Self{
-        Self{base: EnemyBase(Entity::new(id_gen, pos, velo).health(64), 0), shield_health: 64}
+        Self{base: EnemyBase::new(id_gen, pos, velo).health(64).shape(Shape::Circle(BOSS_SIZE)), shield_health: 64}
+    }
+}
+
+/// Boss arriving at the end of a wave, which changes its attack and movement in
+/// phases as it takes damage.
+pub struct StageBoss{
+    pub base: EnemyBase,
+    pub kind: &'static BossKind,
+    /// index into kind.phases
+    pub phase: usize,
+    /// time when it arrived, for the time bonus
+    pub arrival: u32,
+}
+
+impl StageBoss{
+    pub fn new(id_gen: &mut u32, kind: &'static BossKind, time: u32) -> Self{
+        let mut base = EnemyBase::new(id_gen, [WIDTH as f64 / 2., 0.], [0., 1.])
+            .health(kind.health).shape(Shape::Circle(STAGE_BOSS_SIZE));
+        base.movement = kind.phases[0].movement.movement();
+        Self{base, kind, phase: 0, arrival: time}
+    }
+
+    pub fn health(&self) -> i32{
+        self.base.ent.health
+    }
+
+    pub fn current_phase(&self) -> &'static BossPhase{
+        &self.kind.phases[self.phase]
+    }
+
+    /// Moves on to the phase for the remaining health.  Returns true if the phase changed.
+    pub fn update_phase(&mut self) -> bool{
+        let phase = self.kind.phase_for(self.base.ent.health);
+        if self.phase < phase {
+            self.phase = phase;
+            self.base.movement = self.current_phase().movement.movement();
+            true
+        }
+        else{
+            false
+        }
+    }
+
+    /// Updates the phase, and leaves the stage once the time bonus has run out so
+    /// that the waves can go on.
+    pub fn update(&mut self, time: u32){
+        self.update_phase();
+        let movement = &mut self.base.movement;
+        if BOSS_TIME_LIMIT <= time.saturating_sub(self.arrival) && movement.retreat_after.is_none() {
+            movement.retreat_after = Some(movement.age);
+        }
     }
 }
 
@@ -312,20 +1251,33 @@ pub enum Enemy{
     Boss(EnemyBase),
     ShieldedBoss(ShieldedBoss),
     SpiralEnemy(EnemyBase),
+    /// Holds its position and keeps the barrel pointed at the player
+    Turret(EnemyBase),
+    /// Doesn't shoot, but accelerates into the ship
+    Kamikaze(EnemyBase),
+    /// Breaks into Enemy1s when destroyed
+    Splitter(EnemyBase),
+    /// Slow and tough, launching Enemy1 escorts periodically
+    Carrier(EnemyBase),
+    StageBoss(StageBoss),
 }
 
 impl Enemy{
     pub fn get_base(&self) -> &Entity{
         match &self {
-            &Enemy::Enemy1(base) | &Enemy::Boss(base) | &Enemy::SpiralEnemy(base) => &base.0,
-            &Enemy::ShieldedBoss(boss) => &boss.base.0
+            &Enemy::Enemy1(base) | &Enemy::Boss(base) | &Enemy::SpiralEnemy(base) => &base.ent,
+            &Enemy::Turret(base) | &Enemy::Kamikaze(base) | &Enemy::Splitter(base) | &Enemy::Carrier(base) => &base.ent,
+            &Enemy::ShieldedBoss(boss) => &boss.base.ent,
+            &Enemy::StageBoss(boss) => &boss.base.ent,
         }
     }
 
     pub fn get_base_mut(&mut self) -> &mut EnemyBase{
         match self {
             Enemy::Enemy1(ref mut base) | Enemy::Boss(ref mut base) | Enemy::SpiralEnemy(ref mut base) => base,
-            Enemy::ShieldedBoss(ref mut boss) => &mut boss.base
+            Enemy::Turret(ref mut base) | Enemy::Kamikaze(ref mut base) | Enemy::Splitter(ref mut base) | Enemy::Carrier(ref mut base) => base,
+            Enemy::ShieldedBoss(ref mut boss) => &mut boss.base,
+            Enemy::StageBoss(ref mut boss) => &mut boss.base,
         }
     }
 
@@ -335,109 +1287,165 @@ impl Enemy{
 
     pub fn damage(&mut self, val: i32){
         match self {
-            Enemy::Enemy1(ref mut base) | Enemy::Boss(ref mut base) | Enemy::SpiralEnemy(ref mut base) =>
-                base.0.health -= val,
             Enemy::ShieldedBoss(ref mut boss) => {
                 if boss.shield_health < 16 {
-                    boss.base.0.health -= val
+                    boss.base.ent.health -= val
                 }
                 else {
                     boss.shield_health -= val
                 }
             }
+            _ => self.get_base_mut().ent.health -= val,
         }
     }
 
-    pub fn predicted_damage(&self) -> i32 {
+    /// Bombs are not blocked by shields; they damage the shield and the body at once.
+    pub fn bomb_damage(&mut self, val: i32){
         match self {
-            Enemy::Enemy1(base) | Enemy::Boss(base) | Enemy::SpiralEnemy(base) => base.1,
-            Enemy::ShieldedBoss(boss) => boss.base.1
+            Enemy::ShieldedBoss(ref mut boss) => {
+                boss.shield_health = std::cmp::max(0, boss.shield_health - val);
+                boss.base.ent.health -= val;
+            }
+            _ => self.damage(val)
         }
     }
 
-    pub fn add_predicted_damage(&mut self, val: i32){
-        let e = self.get_base_mut();
-        e.1 += val;
-    }
-
     pub fn total_health(&self) -> i32{
         match self {
             Enemy::ShieldedBoss(boss) => {
-                boss.base.0.health + boss.shield_health
+                boss.base.ent.health + boss.shield_health
             },
             _ => self.get_base().health
         }
     }
 
-    pub fn drop_item(&self, ent: Entity) -> Item{
+    pub fn drop_table(&self) -> &'static DropTable{
         match self {
-            Enemy::Enemy1(_) => Item::PowerUp(ent),
-            _ => Item::PowerUp10(ent),
+            Enemy::Enemy1(_) | Enemy::Turret(_) | Enemy::Kamikaze(_) => &ENEMY1_DROPS,
+            Enemy::Boss(_) | Enemy::Carrier(_) | Enemy::StageBoss(_) => &BOSS_DROPS,
+            Enemy::ShieldedBoss(_) => &SHIELDED_BOSS_DROPS,
+            Enemy::SpiralEnemy(_) | Enemy::Splitter(_) => &SPIRAL_ENEMY_DROPS,
         }
     }
 
-    fn gen_bullets(&mut self, id_gen: &mut u32, bullets: &mut std::collections::HashMap<u32, Projectile>,
-            rng: &mut rand::rngs::ThreadRng, create_fn: impl Fn(BulletBase) -> Projectile) {
-        let x: i32 = rng.gen_range(0, 256);
-        if x == 0 {
-            use std::f64::consts::PI;
-            let bullet_count = 10;
-            let phase_offset = rng.gen::<f64>() * PI;
-            for i in 0..bullet_count {
-                let angle = 2. * PI * i as f64 / bullet_count as f64 + phase_offset;
-                let eb = create_fn(BulletBase(Entity::new(
-                    id_gen,
-                    self.get_base().pos,
-                    vec2_scale([angle.cos(), angle.sin()], 1.))
-                    .rotation(angle as f32)));
-                bullets.insert(eb.get_id(), eb);
-            }
-        }
+    /// Rolls the drop table and returns the dropped item if any.
+    pub fn drop_item(&self, id_gen: &mut u32, rng: &mut rand::rngs::ThreadRng) -> Option<Item>{
+        self.drop_table().roll(rng).map(|kind|
+            Item::new(kind, Entity::new(id_gen, self.get_base().pos, [0., ITEM_SPEED])))
     }
 
-    pub fn animate(&mut self, id_gen: &mut u32, bullets: &mut std::collections::HashMap<u32, Projectile>, rng: &mut rand::rngs::ThreadRng, time: u32) -> Option<DeathReason>{
+    /// Returns the shot pattern of this enemy type at the difficulty level and
+    /// the average frames between volleys, or None if it doesn't shoot.
+    pub fn shot_pattern(&self, level: u32) -> Option<(ShotPattern, u32)> {
+        let level = std::cmp::min(level, 10);
+        Some(match self {
+            Enemy::Enemy1(_) => (if level < 2 { ShotPattern::Aimed } else { ShotPattern::Lead },
+                std::cmp::max(64 - level * 4, 24)),
+            Enemy::Boss(_) => (ShotPattern::NWay(3 + 2 * std::cmp::min(level / 2, 3)),
+                std::cmp::max(256 - level * 16, 96)),
+            Enemy::ShieldedBoss(_) => (ShotPattern::Lead, std::cmp::max(128 - level * 8, 48)),
+            Enemy::SpiralEnemy(_) => (ShotPattern::Ring(10), 256),
+            Enemy::Turret(_) => (if level < 2 { ShotPattern::Aimed } else { ShotPattern::Lead },
+                std::cmp::max(96 - level * 4, 48)),
+            Enemy::Kamikaze(_) => return None,
+            Enemy::Splitter(_) => (ShotPattern::Ring(6), 160),
+            Enemy::Carrier(_) => (ShotPattern::NWay(3), std::cmp::max(192 - level * 8, 96)),
+            Enemy::StageBoss(boss) => (boss.current_phase().pattern, boss.current_phase().period),
+        })
+    }
 
-        if self.is_boss() {
-            self.gen_bullets(id_gen, bullets, rng, |base| Projectile::new_phase(base));
+    /// Fires a volley of the given pattern toward the player.
+    pub fn shoot(&self, pattern: ShotPattern, level: u32, id_gen: &mut u32, bullets: &mut std::collections::HashMap<u32, Projectile>,
+            rng: &mut rand::rngs::ThreadRng, player: Option<&Entity>) {
+        let create_fn: fn(BulletBase) -> Projectile = match self {
+            Enemy::Boss(_) => Projectile::new_phase,
+            Enemy::SpiralEnemy(_) => Projectile::new_spiral,
+            Enemy::StageBoss(boss) => boss.current_phase().bullet,
+            _ => Projectile::new_enemy_bullet,
+        };
+        let pos = self.get_base().pos;
+        let speed = enemy_shot_speed(level);
+        for angle in pattern.angles(pos, speed, player, rng) {
+            let eb = create_fn(BulletBase::new(Entity::new(
+                id_gen,
+                pos,
+                vec2_scale([angle.cos(), angle.sin()], speed))
+                .rotation(angle as f32)));
+            bullets.insert(eb.get_id(), eb);
         }
-        else if let Enemy::SpiralEnemy(_) = self {
-            self.gen_bullets(id_gen, bullets, rng, |base| Projectile::new_spiral(base));
+    }
+
+    /// Moves and shoots for a frame.  While the ship is dead, shots that would be
+    /// aimed at the wreck parked off-screen go straight down instead.
+    pub fn animate(&mut self, id_gen: &mut u32, bullets: &mut std::collections::HashMap<u32, Projectile>, rng: &mut rand::rngs::ThreadRng, time: u32,
+            player: &Entity, player_alive: bool, level: u32) -> Option<DeathReason>{
+
+        if let Enemy::StageBoss(boss) = self {
+            boss.update(time);
         }
-        else{
-            let x: i32 = rng.gen_range(0, 64);
-            if x == 0 {
-                let eb = Projectile::EnemyBullet(BulletBase(Entity::new(
-                    id_gen,
-                    self.get_base().pos,
-                    [rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5])));
-                bullets.insert(eb.get_id(), eb);
+
+        if let Some((pattern, period)) = self.shot_pattern(level) {
+            if rng.gen_range(0, period) == 0 {
+                self.shoot(pattern, level, id_gen, bullets, rng, if player_alive { Some(player) } else { None });
             }
         }
 
+        {
+            let base = self.get_base_mut();
+            base.movement.update(&mut base.ent, player);
+        }
+
         match self {
-            Enemy::Enemy1(ref mut base) | Enemy::Boss(ref mut base)
-                => base.0.animate(),
+            Enemy::Enemy1(ref mut base) | Enemy::Boss(ref mut base) | Enemy::Splitter(ref mut base) | Enemy::Carrier(ref mut base)
+                => base.ent.animate(),
+            Enemy::Turret(ref mut base) => {
+                let delta = vec2_sub(player.pos, base.ent.pos);
+                base.ent.rotation = (delta[1].atan2(delta[0]) + std::f64::consts::FRAC_PI_2) as f32;
+                base.ent.animate()
+            }
+            Enemy::Kamikaze(ref mut base) => {
+                let delta = vec2_sub(player.pos, base.ent.pos);
+                if std::f64::EPSILON < vec2_square_len(delta) {
+                    let velo = vec2_add(base.ent.velo, vec2_scale(vec2_normalized(delta), KAMIKAZE_ACCEL));
+                    let speed = vec2_len(velo);
+                    base.ent.velo = if KAMIKAZE_MAX_SPEED < speed { vec2_scale(velo, KAMIKAZE_MAX_SPEED / speed) } else { velo };
+                }
+                base.ent.rotation = (base.ent.velo[1].atan2(base.ent.velo[0]) + std::f64::consts::FRAC_PI_2) as f32;
+                if let Some(death_reason) = base.ent.hits_player(player) {
+                    return Some(death_reason);
+                }
+                base.ent.animate()
+            }
             Enemy::ShieldedBoss(ref mut boss) => {
                 if boss.shield_health < 64 && time % 8 == 0 {
                     boss.shield_health += 1;
                 }
-                boss.base.0.animate()
+                boss.base.ent.animate()
             }
             Enemy::SpiralEnemy(ref mut base) => {
-                base.0.rotation -= std::f32::consts::PI * 0.01;
-                base.0.animate()
+                base.ent.rotation -= std::f32::consts::PI * 0.01;
+                base.ent.animate()
             }
+            Enemy::StageBoss(ref mut boss) => boss.base.ent.animate(),
         }
     }
 
     pub fn draw(&self, context: &Context, g: &mut G2d, assets: &Assets){
         self.get_base().draw_tex(context, g, match self {
             Enemy::Enemy1(_) => &assets.enemy_tex,
-            Enemy::Boss(_) | Enemy::ShieldedBoss(_) => &assets.boss_tex,
+            Enemy::Boss(_) | Enemy::ShieldedBoss(_) | Enemy::StageBoss(_) => &assets.boss_tex,
             Enemy::SpiralEnemy(_) => &assets.spiral_enemy_tex,
-        }, if let Enemy::SpiralEnemy(_) = self { Some(0.5) } else { None });
+            Enemy::Turret(_) => &assets.turret_tex,
+            Enemy::Kamikaze(_) => &assets.kamikaze_tex,
+            Enemy::Splitter(_) => &assets.splitter_tex,
+            Enemy::Carrier(_) => &assets.carrier_tex,
+        }, match self {
+            Enemy::SpiralEnemy(_) => Some(0.5),
+            Enemy::StageBoss(_) => Some(STAGE_BOSS_SIZE / BOSS_SIZE),
+            _ => None
+        });
         if let Enemy::ShieldedBoss(ref boss) = self {
-            let pos = &boss.base.0.pos;
+            let pos = &boss.base.ent.pos;
             let tex2 = &assets.shield_tex;
             let centerize = translate([-(tex2.get_width() as f64 / 2.), -(tex2.get_height() as f64 / 2.)]);
             let rotmat = rotate_radians(0 as f64);
@@ -449,30 +1457,128 @@ impl Enemy{
         }
     }
 
-    pub fn test_hit(&self, rect: [f64; 4]) -> bool{
-        let rect2 = self.get_bb();
-        rect[0] < rect2[2] && rect2[0] < rect[2] && rect[1] < rect2[3] && rect2[1] < rect[3]
+    pub fn hits(&self, collider: &Collider) -> bool{
+        self.collider().intersects(collider)
     }
 
-    pub fn get_bb(&self) -> [f64; 4]{
-        let size = if let Enemy::ShieldedBoss(boss) = self { boss.shield_health as f64 } else { ENEMY_SIZE };
-        let e = self.get_base();
-        [e.pos[0] - size, e.pos[1] - size, e.pos[0] + size, e.pos[1] + size]
+    /// True while the shield of ShieldedBoss covers the body.
+    pub fn is_shielded(&self) -> bool{
+        match self {
+            Enemy::ShieldedBoss(boss) => BOSS_SIZE < boss.shield_health as f64,
+            _ => false
+        }
+    }
+
+    /// The shield of ShieldedBoss covers the body as long as it is larger.
+    pub fn collider(&self) -> Collider{
+        match self {
+            Enemy::ShieldedBoss(boss) if BOSS_SIZE < boss.shield_health as f64 =>
+                Shape::Circle(boss.shield_health as f64).at(boss.base.ent.pos, 0.),
+            _ => self.get_base().collider()
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        match self {
+            Enemy::Enemy1(_) => ENEMY1_SCORE,
+            Enemy::Boss(_) => BOSS_SCORE,
+            Enemy::ShieldedBoss(_) => SHIELDED_BOSS_SCORE,
+            Enemy::SpiralEnemy(_) => SPIRAL_ENEMY_SCORE,
+            Enemy::Turret(_) => TURRET_SCORE,
+            Enemy::Kamikaze(_) => KAMIKAZE_SCORE,
+            Enemy::Splitter(_) => SPLITTER_SCORE,
+            Enemy::Carrier(_) => CARRIER_SCORE,
+            Enemy::StageBoss(boss) => boss.kind.score,
+        }
+    }
+
+    /// Enemies born from this one when it's destroyed.
+    pub fn split(&self, id_gen: &mut u32) -> Vec<Enemy>{
+        if let Enemy::Splitter(base) = self {
+            use std::f64::consts::PI;
+            let pos = base.ent.pos;
+            (0..SPLITTER_CHILDREN).map(|i| {
+                let angle = PI / 2. + (i as f64 - (SPLITTER_CHILDREN - 1) as f64 / 2.) * PI / 4.;
+                Enemy::new_enemy1(id_gen, pos, vec2_scale([angle.cos(), angle.sin()], 1.5))
+            }).collect()
+        }
+        else{
+            vec![]
+        }
+    }
+
+    /// Escorts launched by a Carrier in this frame.
+    pub fn launch_escorts(&self, id_gen: &mut u32) -> Vec<Enemy>{
+        match self {
+            // Counted from its own spawn so that carriers don't all launch at once
+            Enemy::Carrier(base) if 0 < base.movement.age && base.movement.age % CARRIER_LAUNCH_PERIOD == 0 => {
+                let pos = base.ent.pos;
+                vec![
+                    Enemy::new_enemy1(id_gen, vec2_add(pos, [-CARRIER_SIZE, 0.]), [-0.5, 1.]),
+                    Enemy::new_enemy1(id_gen, vec2_add(pos, [CARRIER_SIZE, 0.]), [0.5, 1.]),
+                ]
+            }
+            _ => vec![]
+        }
     }
 
     pub fn is_boss(&self) -> bool {
         match self {
-            Enemy::Boss(_) | Enemy::ShieldedBoss(_) => true,
+            Enemy::Boss(_) | Enemy::ShieldedBoss(_) | Enemy::StageBoss(_) => true,
             _ => false
         }
     }
 
+    pub fn as_stage_boss(&self) -> Option<&StageBoss> {
+        if let Enemy::StageBoss(boss) = self { Some(boss) } else { None }
+    }
+
+    pub fn new_enemy1(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
+        Enemy::Enemy1(EnemyBase::new(id_gen, pos, velo).health(3).shape(Shape::Circle(ENEMY_SIZE)))
+    }
+
+    /// Replaces the movement component, which is straight by default.
+    pub fn with_movement(mut self, movement: Movement) -> Enemy {
+        self.get_base_mut().movement = movement;
+        self
+    }
+
+    pub fn new_boss(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
+        Enemy::Boss(EnemyBase::new(id_gen, pos, velo).health(64).shape(Shape::Circle(BOSS_SIZE)))
+    }
+
     pub fn new_spiral(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
-        Enemy::SpiralEnemy(EnemyBase::new(id_gen, pos, velo))
+        Enemy::SpiralEnemy(EnemyBase::new(id_gen, pos, velo).shape(Shape::Circle(BOSS_SIZE)))
+    }
+
+    pub fn new_turret(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
+        Enemy::Turret(EnemyBase::new(id_gen, pos, velo).health(8).shape(Shape::Circle(TURRET_SIZE)))
+    }
+
+    pub fn new_kamikaze(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
+        Enemy::Kamikaze(EnemyBase::new(id_gen, pos, velo).health(2).shape(Shape::Circle(KAMIKAZE_SIZE)))
+    }
+
+    pub fn new_splitter(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
+        Enemy::Splitter(EnemyBase::new(id_gen, pos, velo).health(12).shape(Shape::Circle(SPLITTER_SIZE)))
+    }
+
+    pub fn new_carrier(id_gen: &mut u32, pos: [f64; 2], velo: [f64; 2]) -> Enemy {
+        Enemy::Carrier(EnemyBase::new(id_gen, pos, velo).health(48).shape(Shape::Circle(CARRIER_SIZE)))
     }
 }
 
-pub struct BulletBase(pub Entity);
+pub struct BulletBase{
+    pub ent: Entity,
+    /// true once the projectile has grazed the player
+    pub grazed: bool,
+}
+
+impl BulletBase{
+    pub fn new(ent: Entity) -> Self{
+        Self{ent, grazed: false}
+    }
+}
 
 pub enum Projectile{
     Bullet(BulletBase),
@@ -487,41 +1593,89 @@ pub enum Projectile{
         speed: f64,
         traveled: f64,
     },
-    Missile{base: BulletBase, target: u32, trail: Vec<[f64; 2]>}
+    /// Homing missile.  `target` is assigned by `Reservations::update`.
+    Missile{base: BulletBase, target: u32, trail: Vec<[f64; 2]>},
+    /// Piercing shot damaging each enemy in `hit` only once
+    ChargeShot{base: BulletBase, radius: f64, hit: Vec<u32>},
+    /// Homing laser of the lock-on weapon.  `volley` is the number of lasers
+    /// launched together and `killed` is set if it finished off an enemy.
+    Laser{base: BulletBase, target: u32, volley: u32, killed: bool, trail: Vec<[f64; 2]>},
 }
 
-const MISSILE_DETECTION_RANGE: f64 = 256.;
 const MISSILE_HOMING_SPEED: f64 = 0.25;
 const MISSILE_TRAIL_LENGTH: usize = 20;
-const MISSILE_DAMAGE: i32 = 5;
 
 impl Projectile{
+    pub fn new_bullet(base: BulletBase) -> Projectile{
+        Projectile::Bullet(BulletBase::new(base.ent.shape(Shape::Capsule{half_length: 4., radius: 4.})))
+    }
+
+    pub fn new_enemy_bullet(base: BulletBase) -> Projectile{
+        Projectile::EnemyBullet(BulletBase::new(base.ent.shape(Shape::Circle(4.))))
+    }
+
     pub fn new_phase(base: BulletBase) -> Projectile{
-        let velo = base.0.velo;
-        Projectile::PhaseBullet{base, velo, phase: 0.}
+        let velo = base.ent.velo;
+        Projectile::PhaseBullet{base: BulletBase::new(base.ent.shape(Shape::Obb([6., 3.]))), velo, phase: 0.}
     }
 
     pub fn new_spiral(base: BulletBase) -> Projectile{
-        let speed = vec2_len(base.0.velo);
-        Projectile::SpiralBullet{base, speed, traveled: 0.}
+        let speed = vec2_len(base.ent.velo);
+        Projectile::SpiralBullet{base: BulletBase::new(base.ent.shape(Shape::Obb([6., 2.]))), speed, traveled: 0.}
+    }
+
+    pub fn new_missile(base: BulletBase) -> Projectile{
+        Projectile::Missile{base: BulletBase::new(base.ent.shape(Shape::Obb([3., 6.]))), target: 0, trail: vec!()}
+    }
+
+    pub fn new_laser(base: BulletBase, target: u32, volley: u32) -> Projectile{
+        Projectile::Laser{base: BulletBase::new(base.ent.shape(Shape::Circle(3.))), target, volley, killed: false, trail: vec!()}
+    }
+
+    pub fn new_charge_shot(base: BulletBase, radius: f64) -> Projectile{
+        Projectile::ChargeShot{base: BulletBase::new(base.ent.shape(Shape::Circle(radius))), radius, hit: vec!()}
     }
 
     pub fn get_base<'b>(&'b self) -> &'b BulletBase{
         match &self {
             &Projectile::Bullet(base) | &Projectile::EnemyBullet(base) => base,
             &Projectile::PhaseBullet{base, ..} | &Projectile::SpiralBullet{base, ..} => base,
-            &Projectile::Missile{base, target: _, trail: _} => base
+            &Projectile::Missile{base, target: _, trail: _} => base,
+            &Projectile::ChargeShot{base, ..} | &Projectile::Laser{base, ..} => base,
+        }
+    }
+
+    pub fn get_base_mut(&mut self) -> &mut BulletBase{
+        match self {
+            Projectile::Bullet(base) | Projectile::EnemyBullet(base) => base,
+            Projectile::PhaseBullet{base, ..} | Projectile::SpiralBullet{base, ..} => base,
+            Projectile::Missile{base, ..} => base,
+            Projectile::ChargeShot{base, ..} | Projectile::Laser{base, ..} => base,
         }
     }
-    // pub fn get_base_mut(&'a mut self) -> &'a mut BulletBase{
-    //     match &mut self {
-    //         &mut Projectile::Bullet(base) | &mut Projectile::Bullet(base) => &mut base,
-    //         &mut Projectile::Missile(base, _) => &mut base
-    //     }
-    // }
 
     pub fn get_id(&self) -> u32{
-        self.get_base().0.id
+        self.get_base().ent.id
+    }
+
+    pub fn is_enemy(&self) -> bool{
+        match self {
+            Projectile::Bullet(_) | Projectile::Missile{..} | Projectile::ChargeShot{..} | Projectile::Laser{..} => false,
+            _ => true
+        }
+    }
+
+    /// Returns true only the first time an enemy projectile grazes the player.
+    pub fn test_graze(&mut self, player: &Entity) -> bool{
+        if !self.is_enemy() {
+            return false;
+        }
+        let base = self.get_base_mut();
+        if base.grazed || !base.ent.grazes_player(player) {
+            return false;
+        }
+        base.grazed = true;
+        true
     }
 
     pub fn get_type(&self) -> &str{
@@ -530,14 +1684,16 @@ impl Projectile{
             &Projectile::PhaseBullet{..} => "PhaseBullet",
             &Projectile::SpiralBullet{..} => "SpiralBullet",
             &Projectile::Missile{..} => "Missile",
+            &Projectile::ChargeShot{..} => "ChargeShot",
+            &Projectile::Laser{..} => "Laser",
         }
     }
 
-    fn animate_player_bullet(mut base: &mut BulletBase, enemies: &mut Vec<Enemy>, mut _player: &mut Entity) -> Option<DeathReason>{
-        let bbox = Self::get_bb_base(base);
-        let &mut BulletBase(ent) = &mut base;
+    fn animate_player_bullet(base: &mut BulletBase, enemies: &mut Vec<Enemy>, mut _player: &mut Entity) -> Option<DeathReason>{
+        let collider = base.ent.swept_collider();
+        let ent = &mut base.ent;
         for enemy in enemies.iter_mut() {
-            if enemy.test_hit(bbox) {
+            if enemy.hits(&collider) {
                 enemy.damage(ent.health);
                 ent.health = 0;
                 break;
@@ -546,8 +1702,8 @@ impl Projectile{
         ent.animate()
     }
 
-    fn animate_enemy_bullet(mut base: &mut BulletBase, _enemies: &mut Vec<Enemy>, mut player: &mut Entity) -> Option<DeathReason>{
-        let &mut BulletBase(ent) = &mut base;
+    fn animate_enemy_bullet(base: &mut BulletBase, _enemies: &mut Vec<Enemy>, mut player: &mut Entity) -> Option<DeathReason>{
+        let ent = &mut base.ent;
         if let Some(death_reason) = ent.hits_player(player) {
             player.health -= ent.health;
             return Some(death_reason)
@@ -564,83 +1720,89 @@ impl Projectile{
                 Self::animate_enemy_bullet(base, enemies, player)
             },
             Projectile::PhaseBullet{base, velo, phase} => {
-                base.0.velo = vec2_scale(*velo, (phase.sin() + 1.) / 2.);
+                base.ent.velo = vec2_scale(*velo, (phase.sin() + 1.) / 2.);
                 *phase += 0.02 * std::f64::consts::PI;
                 Self::animate_enemy_bullet(base, enemies, player)
             }
             Projectile::SpiralBullet{base, speed, traveled} => {
-                let rotation = base.0.rotation as f64 - 0.02 * std::f64::consts::PI / (*traveled * 0.05 + 1.);
-                base.0.rotation = rotation as f32;
-                base.0.velo = vec2_scale([rotation.cos(), rotation.sin()], *speed);
+                let rotation = base.ent.rotation as f64 - 0.02 * std::f64::consts::PI / (*traveled * 0.05 + 1.);
+                base.ent.rotation = rotation as f32;
+                base.ent.velo = vec2_scale([rotation.cos(), rotation.sin()], *speed);
                 *traveled += *speed;
                 Self::animate_enemy_bullet(base, enemies, player)
             }
             Projectile::Missile{base, target, trail} => {
-                if *target == 0 {
-                    let best = enemies.iter_mut().fold((0, 1e5, None), |bestpair, enemy| {
-                        let e = enemy.get_base();
-                        let dist = vec2_len(vec2_sub(base.0.pos, e.pos));
-                        if dist < MISSILE_DETECTION_RANGE && dist < bestpair.1 && enemy.predicted_damage() < enemy.total_health() {
-                            (e.id, dist, Some(enemy))
-                        }
-                        else{
-                            bestpair
-                        }
-                    });
-                    *target = best.0;
-                    if let Some(enemy) = best.2 {
-                        enemy.add_predicted_damage(MISSILE_DAMAGE);
-                        println!("Add predicted damage: {} -> {}", enemy.predicted_damage() - MISSILE_DAMAGE, enemy.predicted_damage());
-                    }
-                }
-                else if let Some(target_enemy) = enemies.iter().find(|e| e.get_id() == *target) {
+                if let Some(target_enemy) = enemies.iter().find(|e| e.get_id() == *target) {
                     let target_ent = target_enemy.get_base();
-                    let norm = vec2_normalized(vec2_sub(target_ent.pos, base.0.pos));
+                    let norm = vec2_normalized(vec2_sub(target_ent.pos, base.ent.pos));
                     let desired_velo = vec2_scale(norm, MISSILE_SPEED);
-                    let desired_diff = vec2_sub(desired_velo, base.0.velo);
+                    let desired_diff = vec2_sub(desired_velo, base.ent.velo);
                     if std::f64::EPSILON < vec2_square_len(desired_diff) {
-                        base.0.velo = if vec2_square_len(desired_diff) < MISSILE_HOMING_SPEED * MISSILE_HOMING_SPEED {
+                        base.ent.velo = if vec2_square_len(desired_diff) < MISSILE_HOMING_SPEED * MISSILE_HOMING_SPEED {
                             desired_velo
                         }
                         else{
                             let desired_diff_norm = vec2_normalized(desired_diff);
-                            vec2_add(base.0.velo, vec2_scale(desired_diff_norm, MISSILE_HOMING_SPEED))
+                            vec2_add(base.ent.velo, vec2_scale(desired_diff_norm, MISSILE_HOMING_SPEED))
                         };
-                        let angle = base.0.velo[1].atan2(base.0.velo[0]);
-                        base.0.rotation = (angle + std::f64::consts::FRAC_PI_2) as f32;
+                        let angle = base.ent.velo[1].atan2(base.ent.velo[0]);
+                        base.ent.rotation = (angle + std::f64::consts::FRAC_PI_2) as f32;
                         let (s, c) = angle.sin_cos();
-                        base.0.velo[0] = MISSILE_SPEED * c;
-                        base.0.velo[1] = MISSILE_SPEED * s;
+                        base.ent.velo[0] = MISSILE_SPEED * c;
+                        base.ent.velo[1] = MISSILE_SPEED * s;
+                    }
+                }
+                if MISSILE_TRAIL_LENGTH < trail.len() {
+                    trail.remove(0);
+                }
+                trail.push(base.ent.pos);
+                Self::animate_player_bullet(base, enemies, player)
+            }
+            Projectile::ChargeShot{base, hit, ..} => {
+                let collider = base.ent.swept_collider();
+                for enemy in enemies.iter_mut() {
+                    if !hit.contains(&enemy.get_id()) && enemy.hits(&collider) {
+                        enemy.damage(base.ent.health);
+                        hit.push(enemy.get_id());
                     }
                 }
-                else{
-                    *target = 0
+                base.ent.animate()
+            }
+            Projectile::Laser{base, target, killed, trail, ..} => {
+                let damage = base.ent.health;
+                if let Some(target_enemy) = enemies.iter().find(|e| e.get_id() == *target) {
+                    // Turn sharply toward the target, much faster than missiles
+                    let delta = vec2_sub(target_enemy.get_base().pos, base.ent.pos);
+                    if std::f64::EPSILON < vec2_square_len(delta) {
+                        let desired_velo = vec2_scale(vec2_normalized(delta), LASER_SPEED);
+                        let velo = vec2_add(base.ent.velo, vec2_scale(vec2_sub(desired_velo, base.ent.velo), LASER_STEERING));
+                        base.ent.velo = vec2_scale(vec2_normalized(velo), LASER_SPEED);
+                    }
                 }
                 if MISSILE_TRAIL_LENGTH < trail.len() {
                     trail.remove(0);
                 }
-                trail.push(base.0.pos);
-                let res = Self::animate_player_bullet(base, enemies, player);
-                if let Some(_) = res {
-                    if let Some(target_enemy) = enemies.iter_mut().find(|e| e.get_id() == *target) {
-                        target_enemy.add_predicted_damage(-MISSILE_DAMAGE);
-                        println!("Reduce predicted damage: {} -> {}", target_enemy.predicted_damage() + MISSILE_DAMAGE, target_enemy.predicted_damage());
+                trail.push(base.ent.pos);
+                // Pass through other enemies while the target is still there
+                let has_target = enemies.iter().any(|e| e.get_id() == *target);
+                let collider = base.ent.swept_collider();
+                for enemy in enemies.iter_mut() {
+                    if (!has_target || enemy.get_id() == *target) && enemy.hits(&collider) {
+                        let alive = 0 < enemy.get_base().health;
+                        enemy.damage(damage);
+                        *killed = alive && enemy.get_base().health <= 0;
+                        base.ent.health = 0;
+                        break;
                     }
                 }
-                res
+                base.ent.animate()
             }
         }
     }
 
-    pub fn get_bb_base(base: &BulletBase) -> [f64; 4]{
-        let e = &base.0;
-        [e.pos[0] - BULLET_SIZE, e.pos[1] - BULLET_SIZE, e.pos[0] + BULLET_SIZE, e.pos[1] + BULLET_SIZE]
-    }
-
     #[allow(dead_code)]
     pub fn get_bb(&self) -> [f64; 4]{
-        let e = self.get_base();
-        Self::get_bb_base(e)
+        self.get_base().ent.collider().bounding_box()
     }
 
     pub fn draw(&self, c: &Context, g: &mut G2d, assets: &Assets){
@@ -655,8 +1817,29 @@ impl Projectile{
                 }
             }
         }
-        self.get_base().0.draw_tex(c, g, match self {
-            Projectile::Bullet(_) => &assets.bullet_tex,
+        if let Projectile::Laser{base, trail, ..} = self {
+            let mut iter = trail.iter().enumerate();
+            if let Some(mut prev) = iter.next() {
+                for e in iter {
+                    let f = e.0 as f32 / MISSILE_TRAIL_LENGTH as f32;
+                    line([1., 0.25 + 0.5 * f, 0.5 + 0.5 * f, f], 0.5 + 1.5 * f as f64,
+                        [prev.1[0], prev.1[1], e.1[0], e.1[1]], c.transform, g);
+                    prev = e;
+                }
+            }
+            let pos = base.ent.pos;
+            ellipse([1., 0.75, 0.9, 1.], [pos[0] - 3., pos[1] - 3., 6., 6.], c.transform, g);
+            return;
+        }
+        if let Projectile::ChargeShot{base, radius, ..} = self {
+            let pos = base.ent.pos;
+            let r = radius * 1.5;
+            ellipse([0.5, 0.75, 1., 0.3], [pos[0] - r, pos[1] - r, r * 2., r * 2.], c.transform, g);
+            base.ent.draw_tex(c, g, &assets.bullet_tex, Some(radius / 4.));
+            return;
+        }
+        self.get_base().ent.draw_tex(c, g, match self {
+            Projectile::Bullet(_) | Projectile::ChargeShot{..} | Projectile::Laser{..} => &assets.bullet_tex,
             Projectile::EnemyBullet(_) => &assets.ebullet_tex,
             Projectile::PhaseBullet{..} => &assets.phase_bullet_tex,
             Projectile::SpiralBullet{..} => &assets.spiral_bullet_tex,
@@ -666,22 +1849,123 @@ impl Projectile{
 }
 
 
+/// Kind of an Item without the entity, used to describe drop tables.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemKind{
+    PowerUp,
+    PowerUp10,
+    Bomb,
+    OneUp,
+    Shield,
+    Medal,
+    FullPower,
+}
+
+/// Chance in percent that an enemy drops an item when killed, and the relative
+/// weights of the kinds of items dropped.
+pub struct DropTable{
+    pub rate: u32,
+    pub items: &'static [(ItemKind, u32)],
+}
+
+impl DropTable{
+    /// Picks the item kind for dice in [0, total weight).
+    pub fn pick(&self, dice: u32) -> Option<ItemKind>{
+        let mut accum = 0;
+        for &(kind, weight) in self.items {
+            accum += weight;
+            if dice < accum {
+                return Some(kind);
+            }
+        }
+        None
+    }
+
+    pub fn total_weight(&self) -> u32{
+        self.items.iter().map(|&(_, weight)| weight).sum()
+    }
+
+    pub fn roll(&self, rng: &mut rand::rngs::ThreadRng) -> Option<ItemKind>{
+        let total = self.total_weight();
+        if total == 0 || self.rate <= rng.gen_range(0, 100) {
+            return None;
+        }
+        self.pick(rng.gen_range(0, total))
+    }
+}
+
+pub const ENEMY1_DROPS: DropTable = DropTable{rate: 20, items: &[
+    (ItemKind::PowerUp, 90),
+    (ItemKind::Medal, 10),
+]};
+
+pub const BOSS_DROPS: DropTable = DropTable{rate: 20, items: &[
+    (ItemKind::PowerUp10, 60),
+    (ItemKind::Medal, 20),
+    (ItemKind::Shield, 10),
+    (ItemKind::OneUp, 5),
+    (ItemKind::FullPower, 5),
+]};
+
+pub const SHIELDED_BOSS_DROPS: DropTable = DropTable{rate: 20, items: &[
+    (ItemKind::Bomb, 50),
+    (ItemKind::PowerUp10, 30),
+    (ItemKind::Shield, 10),
+    (ItemKind::OneUp, 5),
+    (ItemKind::FullPower, 5),
+]};
+
+pub const SPIRAL_ENEMY_DROPS: DropTable = DropTable{rate: 20, items: &[
+    (ItemKind::PowerUp10, 70),
+    (ItemKind::Medal, 30),
+]};
+
 pub enum Item{
     PowerUp(Entity),
-    PowerUp10(Entity)
+    PowerUp10(Entity),
+    Bomb(Entity),
+    OneUp(Entity),
+    Shield(Entity),
+    Medal(Entity),
+    FullPower(Entity),
 }
 
 impl Item{
+    pub fn new(kind: ItemKind, ent: Entity) -> Item{
+        match kind {
+            ItemKind::PowerUp => Item::PowerUp(ent.shape(Shape::Circle(4.))),
+            ItemKind::PowerUp10 => Item::PowerUp10(ent.shape(Shape::Circle(8.))),
+            ItemKind::Bomb => Item::Bomb(ent.shape(Shape::Circle(8.))),
+            ItemKind::OneUp => Item::OneUp(ent.shape(Shape::Circle(8.))),
+            ItemKind::Shield => Item::Shield(ent.shape(Shape::Circle(8.))),
+            ItemKind::Medal => Item::Medal(ent.shape(Shape::Circle(8.))),
+            ItemKind::FullPower => Item::FullPower(ent.shape(Shape::Circle(8.))),
+        }
+    }
+
     pub fn get_base(&self) -> &Entity{
         match self {
-            Item::PowerUp(ent) | Item::PowerUp10(ent) => ent,
+            Item::PowerUp(ent) | Item::PowerUp10(ent) | Item::Bomb(ent) | Item::OneUp(ent)
+            | Item::Shield(ent) | Item::Medal(ent) | Item::FullPower(ent) => ent,
+        }
+    }
+
+    pub fn get_base_mut(&mut self) -> &mut Entity{
+        match self {
+            Item::PowerUp(ent) | Item::PowerUp10(ent) | Item::Bomb(ent) | Item::OneUp(ent)
+            | Item::Shield(ent) | Item::Medal(ent) | Item::FullPower(ent) => ent,
         }
     }
 
     pub fn draw(&self, c: &Context, g: &mut G2d, assets: &Assets){
         match self {
             Item::PowerUp(item) => item.draw_tex(c, g, &assets.power_tex, None),
-            Item::PowerUp10(item) => item.draw_tex(c, g, &assets.power2_tex, None)
+            Item::PowerUp10(item) => item.draw_tex(c, g, &assets.power2_tex, None),
+            Item::Bomb(item) => item.draw_tex(c, g, &assets.bomb_tex, None),
+            Item::OneUp(item) => item.draw_tex(c, g, &assets.player_tex, Some(0.5)),
+            Item::Shield(item) => item.draw_tex(c, g, &assets.shield_item_tex, None),
+            Item::Medal(item) => item.draw_tex(c, g, &assets.medal_tex, None),
+            Item::FullPower(item) => item.draw_tex(c, g, &assets.full_power_tex, None),
         }
     }
 
@@ -689,23 +1973,134 @@ impl Item{
         match self {
             Item::PowerUp(_) => 1,
             Item::PowerUp10(_) => 10,
+            _ => 0,
         }
     }
 
-    pub fn animate(&mut self, player: &mut Player) -> Option<DeathReason> {
+    /// Splits `power` into power up items thrown upward in an arc from `pos`.
+    /// Power that doesn't fit in DEATH_SCATTER_MAX_ITEMS items is lost.
+    pub fn scatter(id_gen: &mut u32, pos: [f64; 2], power: u32) -> Vec<Item>{
+        let tens = std::cmp::min((power / 10) as usize, DEATH_SCATTER_MAX_ITEMS);
+        let ones = std::cmp::min((power % 10) as usize, DEATH_SCATTER_MAX_ITEMS - tens);
+        let count = tens + ones;
+        let speed = [2., 3.];
+        // Items leaving the screen are gone for good, so start far enough from the
+        // top and the sides for the whole arc to stay inside
+        let margin = [speed[0] / (1. - ITEM_DRAG), speed[1] * speed[1] / (2. * ITEM_GRAVITY)];
+        let pos = [pos[0].max(margin[0]).min(WIDTH as f64 - margin[0]), pos[1].max(margin[1])];
+        (0..count).map(|i| {
+            use std::f64::consts::PI;
+            // Spread from upper left to upper right
+            let angle = -PI * (0.2 + 0.6 * (i as f64 + 0.5) / count as f64);
+            let ent = Entity::new(id_gen, pos, [speed[0] * angle.cos(), speed[1] * angle.sin()]);
+            Item::new(if i < tens { ItemKind::PowerUp10 } else { ItemKind::PowerUp }, ent)
+        }).collect()
+    }
+
+    fn collect(&self, player: &mut Player) {
+        player.add_power(self.power_value());
         match self {
-            Item::PowerUp(ent) | Item::PowerUp10(ent) => {
-                if let Some(_) = ent.hits_player(&player.base) {
-                    player.power += self.power_value();
-                    return Some(DeathReason::Killed)
-                }
-                ent.animate()
+            Item::Bomb(_) => player.bombs = std::cmp::min(player.bombs + 1, PLAYER_BOMBS_MAX),
+            Item::OneUp(_) => { player.extend(); },
+            Item::Shield(_) => player.shield_time = PLAYER_SHIELD_TIME,
+            Item::Medal(_) => {
+                player.medal_chain += 1;
+                player.score += player.medal_score();
             },
+            Item::FullPower(_) => player.add_power(player.get_weapon().max_power()),
+            _ => {}
+        }
+    }
+
+    /// Text to pop up when the item is collected, called after collecting.
+    pub fn pickup_text(&self, player: &Player) -> Option<String>{
+        match self {
+            Item::Bomb(_) => Some("BOMB".to_string()),
+            Item::OneUp(_) => Some("1UP".to_string()),
+            Item::Shield(_) => Some("SHIELD".to_string()),
+            Item::Medal(_) => Some(format!("{}", player.medal_score())),
+            Item::FullPower(_) => Some("FULL POWER".to_string()),
+            _ => None
         }
     }
+
+    /// Returns true if the item should home in on the player, either because
+    /// the player is close enough or above the auto-collect line.
+    pub fn is_attracted(&self, player: &Player) -> bool{
+        if !player.can_control() {
+            return false;
+        }
+        player.base.pos[1] < ITEM_COLLECT_LINE
+            || vec2_square_len(vec2_sub(player.base.pos, self.get_base().pos)) < ITEM_MAGNET_RADIUS * ITEM_MAGNET_RADIUS
+    }
+
+    pub fn animate(&mut self, player: &mut Player) -> Option<DeathReason> {
+        if player.is_alive() && self.get_base().collider().intersects(&Shape::Circle(PLAYER_PICKUP_RADIUS).at(player.base.pos, 0.)) {
+            self.collect(player);
+            return Some(DeathReason::Killed)
+        }
+        let attracted = self.is_attracted(player);
+        let ent = self.get_base_mut();
+        if attracted {
+            // Steer toward the player, turning gradually so that items fly in a curve
+            let delta = vec2_sub(player.base.pos, ent.pos);
+            let dist = vec2_len(delta);
+            if 0. < dist {
+                let desired = vec2_scale(delta, ITEM_HOMING_SPEED / dist);
+                ent.velo = vec2_add(ent.velo, vec2_scale(vec2_sub(desired, ent.velo), ITEM_STEERING));
+            }
+        }
+        else {
+            // Items thrown in any direction slow down and fall at the same speed
+            ent.velo[0] *= ITEM_DRAG;
+            ent.velo[1] = (ent.velo[1] + ITEM_GRAVITY).min(ITEM_SPEED);
+        }
+        let res = ent.animate();
+        if let (Some(DeathReason::RangeOut), Item::Medal(_)) = (&res, &self) {
+            // Missing a medal resets its value
+            player.medal_chain = 0;
+        }
+        res
+    }
+}
+
+
+
+/// Floating text showing the score awarded at the position of a kill.
+pub struct ScorePopup{
+    pub pos: [f64; 2],
+    pub text: String,
+    pub color: [f32; 3],
+    pub life: u32,
 }
 
+impl ScorePopup{
+    /// Popup for `value` awarded with the combo `multiplier`.
+    pub fn new(pos: [f64; 2], value: u32, multiplier: u32) -> Self{
+        // Multiplied scores stand out in yellow
+        let color = if 1 < multiplier { [1., 1., 0.] } else { [1., 1., 1.] };
+        Self::new_text(pos, format!("{}", value), color)
+    }
 
+    pub fn new_text(pos: [f64; 2], text: String, color: [f32; 3]) -> Self{
+        Self{pos, text, color, life: SCORE_POPUP_TIME}
+    }
+
+    pub fn animate(&mut self) -> Option<DeathReason>{
+        self.pos[1] -= 0.5;
+        self.life -= 1;
+        if self.life == 0 { Some(DeathReason::Killed) } else { None }
+    }
+
+    /// Opacity for fading out at the end of life.
+    pub fn alpha(&self) -> f32{
+        (self.life as f32 / (SCORE_POPUP_TIME / 2) as f32).min(1.)
+    }
+
+    pub fn color(&self) -> [f32; 4]{
+        [self.color[0], self.color[1], self.color[2], self.alpha()]
+    }
+}
 
 pub struct TempEntity<'a>{
     pub base: Entity,
@@ -740,3 +2135,109 @@ impl<'a> TempEntity<'a>{
     }
 }
 
+
+#[test]
+fn test_aimed_shots() {
+    use std::collections::HashMap;
+    let mut id_gen = 0;
+    let mut rng = thread_rng();
+    let mut player = Entity::new(&mut id_gen, [100., 300.], [0., 0.]);
+    let enemy = Enemy::new_enemy1(&mut id_gen, [100., 100.], [0., 0.]);
+    let angle_to = |velo: [f64; 2], target: [f64; 2], pos: [f64; 2]| {
+        let delta = vec2_sub(target, pos);
+        (velo[1].atan2(velo[0]) - delta[1].atan2(delta[0])).abs()
+    };
+
+    // Direct aim
+    let mut bullets = HashMap::new();
+    enemy.shoot(ShotPattern::Aimed, 0, &mut id_gen, &mut bullets, &mut rng, Some(&player));
+    assert_eq!(bullets.len(), 1);
+    let velo = bullets.values().next().unwrap().get_base().ent.velo;
+    assert!(angle_to(velo, player.pos, [100., 100.]) < 1e-6);
+    assert!((vec2_len(velo) - ENEMY_SHOT_SPEED).abs() < 1e-6);
+
+    // N-way spread is centered on the player
+    let angles = ShotPattern::NWay(5).angles([100., 100.], 1., Some(&player), &mut rng);
+    assert_eq!(angles.len(), 5);
+    assert!((angles[2] - std::f64::consts::FRAC_PI_2).abs() < 1e-6);
+    assert!((angles[0] + angles[4] - std::f64::consts::PI).abs() < 1e-6);
+
+    // Leading shot meets the moving player
+    player.velo = [1., 0.];
+    let speed = enemy_shot_speed(4);
+    let target = lead_target([100., 100.], speed, &player);
+    let t = (target[0] - player.pos[0]) / player.velo[0];
+    assert!(0. < t);
+    assert!((vec2_len(vec2_sub(target, [100., 100.])) - speed * t).abs() < 1e-6);
+
+    // Shots can't catch up with a faster player, aim at it instead
+    player.velo = [0., 4.];
+    assert_eq!(lead_target([100., 100.], speed, &player), player.pos);
+
+    // Nothing to aim at while the ship is dead, so shots go straight down
+    for pattern in &[ShotPattern::Aimed, ShotPattern::Lead] {
+        assert_eq!(pattern.angles([50., 100.], speed, None, &mut rng), vec![std::f64::consts::FRAC_PI_2]);
+    }
+
+    // Difficulty raises the speed up to the limit
+    assert!(enemy_shot_speed(0) < enemy_shot_speed(4));
+    assert_eq!(enemy_shot_speed(100), ENEMY_SHOT_SPEED_MAX);
+    assert!(enemy.shot_pattern(10).unwrap().1 < enemy.shot_pattern(0).unwrap().1);
+}
+
+#[test]
+fn test_enemy_archetypes() {
+    use std::collections::HashMap;
+    use super::movement::{Movement, Pattern};
+    let mut id_gen = 0;
+    let mut rng = thread_rng();
+    let mut bullets = HashMap::new();
+    let player = Entity::new(&mut id_gen, [100., 400.], [0., 0.]);
+
+    // Turret stays put and keeps the barrel on the player
+    let mut turret = Enemy::new_turret(&mut id_gen, [200., 100.], [0., 0.])
+        .with_movement(Movement::new(Pattern::SwoopIn{stop: [200., 100.]}));
+    for _ in 0..10 {
+        assert!(turret.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0).is_none());
+    }
+    assert_eq!(turret.get_base().pos, [200., 100.]);
+    let angle = turret.get_base().rotation as f64 - std::f64::consts::FRAC_PI_2;
+    let delta = vec2_sub(player.pos, [200., 100.]);
+    assert!((angle - delta[1].atan2(delta[0])).abs() < 1e-6);
+    assert_eq!(turret.shot_pattern(0).unwrap().0, ShotPattern::Aimed);
+
+    // Kamikaze speeds up toward the ship until it rams it
+    let mut kamikaze = Enemy::new_kamikaze(&mut id_gen, [100., 100.], [0., 0.]);
+    assert!(kamikaze.shot_pattern(0).is_none());
+    let mut speed = 0.;
+    let mut hit = false;
+    for _ in 0..200 {
+        if let Some(reason) = kamikaze.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0) {
+            hit = if let DeathReason::HitPlayer = reason { true } else { false };
+            break;
+        }
+        let new_speed = vec2_len(kamikaze.get_base().velo);
+        assert!(speed <= new_speed && new_speed <= KAMIKAZE_MAX_SPEED + 1e-6);
+        speed = new_speed;
+    }
+    assert!(hit);
+
+    // Splitter breaks into smaller enemies, others don't
+    let splitter = Enemy::new_splitter(&mut id_gen, [100., 100.], [0., 1.]);
+    let children = splitter.split(&mut id_gen);
+    assert_eq!(children.len(), SPLITTER_CHILDREN as usize);
+    assert!(children.iter().all(|e| if let Enemy::Enemy1(_) = e { e.get_base().pos == [100., 100.] } else { false }));
+    assert!(children.iter().all(|e| 0. < e.get_base().velo[1]));
+    assert!(turret.split(&mut id_gen).is_empty());
+
+    // Carrier launches a pair of escorts periodically, but not right on spawn
+    let mut carrier = Enemy::new_carrier(&mut id_gen, [100., 100.], [0., 0.]);
+    assert!(carrier.launch_escorts(&mut id_gen).is_empty());
+    for _ in 1..CARRIER_LAUNCH_PERIOD {
+        carrier.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+        assert!(carrier.launch_escorts(&mut id_gen).is_empty());
+    }
+    carrier.animate(&mut id_gen, &mut bullets, &mut rng, 0, &player, true, 0);
+    assert_eq!(carrier.launch_escorts(&mut id_gen).len(), 2);
+    assert!(splitter.launch_escorts(&mut id_gen).is_empty());
+}
@@ -7,26 +7,35 @@ extern crate rand;
 
 use piston_window::*;
 use rand::prelude::*;
-use piston_window::draw_state::Blend;
 use std::collections::HashMap;
 
 mod consts;
+mod collision;
 mod entity;
+mod weapon;
+mod reservation;
+mod movement;
+mod boss;
 
 use consts::*;
+use crate::collision::Shape;
 use crate::entity::{
     Assets,
     Matrix,
     DeathReason,
     Entity,
     Player,
-    EnemyBase,
     Enemy,
     ShieldedBoss,
-    BulletBase,
+    StageBoss,
     Projectile,
     Item,
+    ScorePopup,
     TempEntity};
+use crate::weapon::{FireContext, WEAPONS, cancel_locks, drop_dead_locks};
+use crate::reservation::Reservations;
+use crate::movement::{Movement, Pattern, wave_movement};
+use crate::boss::{BOSSES, time_bonus};
 
 
 
@@ -42,7 +51,7 @@ fn main() {
     let (assets, mut glyphs) = Assets::new(&mut window);
 
     let mut id_gen = 0;
-    let mut player = Player::new(Entity::new(&mut id_gen, [240., 400.], [0., 0.]));
+    let mut player = Player::new(Entity::new(&mut id_gen, PLAYER_SPAWN_POS, [0., 0.]));
 
     let mut enemies = Vec::<Enemy>::new();
 
@@ -50,14 +59,18 @@ fn main() {
 
     let mut bullets = HashMap::new();
 
+    let mut reservations = Reservations::new();
+
     let mut tent = Vec::<TempEntity>::new();
 
+    let mut popups = Vec::<ScorePopup>::new();
+
     let mut rng = thread_rng();
 
     let mut paused = false;
     let mut game_over = true;
 
-    let [mut shots_bullet, mut shots_missile] = [0, 0];
+    let mut shots = vec![0; WEAPONS.len()];
 
     fn limit_viewport(viewport: &Viewport, ratio: f64, wwidth: u32, wheight: u32) -> Viewport{
         let vp_ratio = (viewport.rect[2] - viewport.rect[0]) as f64 /
@@ -73,17 +86,18 @@ fn main() {
         newvp
     }
 
-    let [mut key_up, mut key_down, mut key_left, mut key_right, mut key_shoot,
-        mut key_change, mut key_pause] = [false; 7];
-
-    #[derive(PartialEq, Clone)]
-    enum Weapon{
-        Bullet,
-        Light,
-        Missile,
-        Lightning,
+    /// Removes enemy bullets matching `filter` and returns where they were.
+    fn cancel_enemy_bullets(bullets: &mut HashMap<u32, Projectile>, filter: impl Fn(&Projectile) -> bool) -> Vec<[f64; 2]>{
+        let cancelled: Vec<u32> = bullets.iter().filter(|(_, b)| b.is_enemy() && filter(b)).map(|(id, _)| *id).collect();
+        cancelled.iter().filter_map(|id| bullets.remove(id)).map(|b| b.get_base().ent.pos).collect()
     }
-    let mut weapon = Weapon::Bullet;
+
+    let [mut key_up, mut key_down, mut key_left, mut key_right, mut key_shoot,
+        mut key_change, mut key_pause, mut key_focus, mut key_bomb] = [false; 9];
+
+    // Latched on the release of the shoot key until the next update consumes it,
+    // so that a release between frames is never missed.
+    let mut shoot_released = false;
 
     while let Some(event) = window.next() {
 
@@ -127,126 +141,130 @@ fn main() {
             };
 
             if !game_over && !paused {
-                if key_up { player.move_up() }
-                if key_down { player.move_down() }
-                if key_left { player.move_left() }
-                if key_right { player.move_right() }
-
-                let shoot_period = if let Weapon::Bullet = weapon { 5 } else { 50 };
-
-                if Weapon::Bullet == weapon || Weapon::Missile == weapon {
-                    if key_shoot && player.cooldown == 0 {
-                        let level = player.power_level() as i32;
-                        player.cooldown += shoot_period;
-                        for i in -1-level..2+level {
-                            let speed = if let Weapon::Bullet = weapon { BULLET_SPEED } else { MISSILE_SPEED };
-                            let mut ent = Entity::new(&mut id_gen, player.base.pos, [i as f64, -speed])
-                                .rotation((i as f32).atan2(speed as f32));
-                            if let Weapon::Bullet = weapon {
-                                shots_bullet += 1;
-                                ent = ent.blend(Blend::Add);
-                                bullets.insert(ent.id, Projectile::Bullet(BulletBase(ent)));
-                            }
-                            else{
-                                shots_missile += 1;
-                                ent = ent.health(5);
-                                bullets.insert(ent.id, Projectile::Missile{base: BulletBase(ent), target: 0, trail: vec!()});
-                            }
-                        }
-                    }
-                    if player.cooldown < 1 {
-                        player.cooldown = 0;
-                    }
-                    else{
-                        player.cooldown -= 1;
-                    }
+                player.focus = key_focus;
+                let key_shoot = key_shoot && player.can_control();
+                let prev_pos = player.base.pos;
+                if player.can_control() {
+                    if key_up { player.move_up() }
+                    if key_down { player.move_down() }
+                    if key_left { player.move_left() }
+                    if key_right { player.move_right() }
                 }
-                else if Weapon::Light == weapon && key_shoot {
-                    // Apparently Piston doesn't allow vertex colored rectangle, we need to 
-                    // draw multiple lines in order to display gradual change in color.
-                    for i in -3..4 {
-                        let f = (4. - (i as i32).abs() as f32) / 4.;
-                        line([f / 3., 0.5 + f / 2., 1., f],
-                            1.,
-                            [player.base.pos[0] + i as f64, player.base.pos[1],
-                            player.base.pos[0] + i as f64, 0.],
-                            context.transform, graphics);
+                player.update_entry();
+                // Enemies lead their shots by this
+                player.base.velo = vecmath::vec2_sub(player.base.pos, prev_pos);
+                player.update_options();
+
+                let weapon = player.get_weapon();
+                let level = player.power_level();
+                let released = std::mem::replace(&mut shoot_released, false) && player.can_control();
+                let holding = key_shoot && weapon.can_charge();
+
+                // Auto-fire stops once the shot is charged enough to be released
+                let firing = key_shoot && !player.is_charged() && player.cooldown == 0 && player.drain_energy(weapon.energy_cost());
+                if !firing || weapon.energy_cost() == 0 {
+                    player.recharge_energy();
+                }
+                if firing {
+                    player.cooldown += weapon.cooldown();
+                }
+
+                drop_dead_locks(&mut player, &enemies, &mut reservations);
+                let (hits, locked) = {
+                    let mut ctx = FireContext::new(&player, &mut enemies, &mut reservations, &mut bullets, &mut id_gen, &mut rng, time);
+                    if released {
+                        shots[player.weapon] += weapon.release(&mut ctx, level, player.charge);
                     }
-                    for enemy in enemies.iter_mut() {
-                        if enemy.test_hit([player.base.pos[0] - LIGHT_WIDTH, 0., player.base.pos[0] + LIGHT_WIDTH, player.base.pos[1]]) {
-                            add_tent(true, &enemy.get_base().pos, &mut id_gen, &mut rng);
-                            enemy.damage(1 + player.power_level() as i32);
+                    let locked = if holding { weapon.hold(&mut ctx, level, player.charge) } else { vec![] };
+                    if firing {
+                        shots[player.weapon] += weapon.fire(&mut ctx, level);
+                        for pos in player.options.iter() {
+                            ctx.pos = *pos;
+                            shots[player.weapon] += weapon.fire_option(&mut ctx, level);
                         }
                     }
+                    weapon.draw(&ctx.effects, &context, graphics);
+                    (ctx.hits, locked)
+                };
+                for pos in hits {
+                    add_tent(true, &pos, &mut id_gen, &mut rng);
+                }
+                if released {
+                    // The lasers carry the reservations from now on
+                    player.locks.clear();
+                }
+                player.locks.extend(locked);
+                player.charge = if holding { player.charge + 1 } else { 0 };
+                if 0 < player.cooldown {
+                    player.cooldown -= 1;
                 }
-                else if Weapon::Lightning == weapon && key_shoot {
-                    let col = [1.,1.,1.,1.];
-                    let col2 = [1.,0.5,1.,0.25];
-                    let nmax = std::cmp::min((player.power_level() + 1 + time % 2) / 2, 31);
-
-                    // Random walk with momentum
-                    fn next_lightning(rng: &mut SmallRng, a: &mut [f64; 4]){
-                        a[2] += LIGHTNING_ACCEL * (rng.gen::<f64>() - 0.5) - a[2] * LIGHTNING_FEEDBACK;
-                        a[3] += LIGHTNING_ACCEL * (rng.gen::<f64>() - 0.5) - a[3] * LIGHTNING_FEEDBACK;
-                        a[0] += a[2];
-                        a[1] += a[3];
-                    }
 
-                    for _ in 0..nmax {
-                        // Use the same seed twice to reproduce random sequence
-                        let seed = {
-                            let mut seed: <SmallRng as SeedableRng>::Seed = Default::default();
-                            rng.fill_bytes(&mut seed);
-                            seed
-                        };
+                if 0 < player.invtime {
+                    player.invtime -= 1;
+                }
+                if 0 < player.shield_time {
+                    player.shield_time -= 1;
+                }
 
-                        // Lambda to call the same lightning sequence twice, first pass for detecting hit enemy
-                        // and second pass for rendering.
-                        let lightning = |seed: &<SmallRng as SeedableRng>::Seed, length: u32, f: &mut dyn FnMut(&[f64; 4]) -> bool| {
-                            let mut rng2 = SmallRng::from_seed(*seed);
-                            let mut a = [player.base.pos[0], player.base.pos[1], 0., -16.];
-                            for i in 0..length {
-                                let ox = a[0];
-                                let oy = a[1];
-                                next_lightning(&mut rng2, &mut a);
-                                let segment = [ox, oy, a[0], a[1]];
-                                if !f(&segment) {
-                                    return i;
-                                }
-                            }
-                            length
-                        };
+                if player.update_death() {
+                    // Clear enemy bullets around the spawn point so that the player
+                    // doesn't die again right after coming back.
+                    let spawn = Shape::Circle(PLAYER_RESPAWN_CLEAR_RADIUS).at(PLAYER_SPAWN_POS, 0.);
+                    for pos in cancel_enemy_bullets(&mut bullets, |b| b.get_base().ent.collider().intersects(&spawn)) {
+                        add_tent(true, &pos, &mut id_gen, &mut rng);
+                    }
+                }
+                else if player.lives == 0 && player.is_alive() {
+                    // The explosion of the last ship has finished
+                    game_over = true;
+                }
 
-                        let length = lightning(&seed, LIGHTNING_VERTICES, &mut |segment: &[f64; 4]| {
-                            let b = [segment[2], segment[3]];
-                            for enemy in enemies.iter_mut() {
-                                let ebb = enemy.get_bb();
-                                if ebb[0] < b[0] + 4. && b[0] - 4. <= ebb[2] && ebb[1] < b[1] + 4. && b[1] - 4. <= ebb[3] {
-                                    enemy.damage(2 + rng.gen_range(0, 3));
-                                    add_tent(true, &b, &mut id_gen, &mut rng);
-                                    return false;
-                                }
-                            }
-                            return true;
-                        });
-                        let hit = length != LIGHTNING_VERTICES;
+                player.update_combo();
+                player.check_extend();
+                if 0 < player.extend_time {
+                    player.extend_time -= 1;
+                }
 
-                        lightning(&seed, length, &mut |segment: &[f64; 4]| {
-                            line(if hit { col } else { col2 }, if hit { 2. } else { 1. }, *segment, context.transform, graphics);
-                            true
-                        });
+                if 0 < player.bomb_time {
+                    if player.bomb_time == BOMB_TIME {
+                        for enemy in enemies.iter_mut() {
+                            add_tent(false, &enemy.get_base().pos, &mut id_gen, &mut rng);
+                            enemy.bomb_damage(BOMB_DAMAGE);
+                        }
                     }
-                }
 
-                if 0 < player.invtime {
-                    player.invtime -= 1;
+                    // Keep cancelling enemy bullets while the bomb is active
+                    for pos in cancel_enemy_bullets(&mut bullets, |_| true) {
+                        add_tent(true, &pos, &mut id_gen, &mut rng);
+                    }
+                    player.bomb_time -= 1;
                 }
             }
 
-            if !game_over {
+            if !game_over && player.is_alive() {
+                // Options glow in the color of the selected weapon
+                let color = player.get_weapon().color();
+                let option_image = Image::new_color([color[0], color[1], color[2], 1.]);
+                let half = assets.sphere_tex.get_width() as f64 / 4.;
+                for pos in player.options.iter() {
+                    let transform = context.transform.trans(pos[0] - half, pos[1] - half).scale(0.5, 0.5);
+                    option_image.draw(&assets.sphere_tex, &context.draw_state, transform, graphics);
+                }
                 if player.invtime == 0 || disptime % 2 == 0 {
                     player.base.draw_tex(&context, graphics, &assets.player_tex, None);
                 }
+                // Blink the shield when it's about to run out
+                if 0 < player.shield_time && (120 < player.shield_time || disptime % 8 < 4) {
+                    player.base.draw_tex(&context, graphics, &assets.shield_tex, Some(0.25));
+                }
+                if player.focus {
+                    // Show the collision core on top of the sprite while focused
+                    let pos = player.base.pos;
+                    let r = PLAYER_HIT_RADIUS + 1.;
+                    ellipse([1., 0.25, 0.25, 1.], [pos[0] - r, pos[1] - r, r * 2., r * 2.], context.transform, graphics);
+                    let r = PLAYER_HIT_RADIUS;
+                    ellipse([1., 1., 1., 1.], [pos[0] - r, pos[1] - r, r * 2., r * 2.], context.transform, graphics);
+                }
             }
 
             if !paused {
@@ -258,7 +276,12 @@ fn main() {
 
             for (i, e) in &mut ((&mut items).iter_mut().enumerate()) {
                 if !paused {
-                    if let Some(_) = e.animate(&mut player) {
+                    if let Some(death_reason) = e.animate(&mut player) {
+                        if let DeathReason::Killed = death_reason {
+                            if let Some(text) = e.pickup_text(&player) {
+                                popups.push(ScorePopup::new_text(e.get_base().pos, text, [0.5, 1., 1.]));
+                            }
+                        }
                         to_delete.push(i);
                         continue;
                     }
@@ -276,14 +299,26 @@ fn main() {
             if !paused {
                 let dice = 256;
                 let wave = time % wave_period;
-                if wave < wave_period * 3 / 4 {
-                    let [enemy_count, boss_count, shielded_boss_count, spiral_count]
-                         = enemies.iter().fold([0; 4],
+                // The random spawner rests while the stage boss is around
+                let boss_present = enemies.iter().any(|e| e.as_stage_boss().is_some());
+                if wave == wave_period * 3 / 4 && !boss_present {
+                    let kind = &BOSSES[(time / wave_period) as usize % BOSSES.len()];
+                    enemies.push(Enemy::StageBoss(StageBoss::new(&mut id_gen, kind, time)));
+                }
+                else if wave < wave_period * 3 / 4 && !boss_present {
+                    let [enemy_count, boss_count, shielded_boss_count, spiral_count,
+                        turret_count, kamikaze_count, splitter_count, carrier_count]
+                         = enemies.iter().fold([0; 8],
                         |mut c, e| match e {
                             Enemy::Enemy1(_) => {c[0] += 1; c},
                             Enemy::Boss(_) => {c[1] += 1; c},
                             Enemy::ShieldedBoss(_) => {c[2] += 1; c},
                             Enemy::SpiralEnemy(_) => {c[3] += 1; c},
+                            Enemy::Turret(_) => {c[4] += 1; c},
+                            Enemy::Kamikaze(_) => {c[5] += 1; c},
+                            Enemy::Splitter(_) => {c[6] += 1; c},
+                            Enemy::Carrier(_) => {c[7] += 1; c},
+                            Enemy::StageBoss(_) => c,
                         });
                     let gen_amount = player.difficulty_level() * 4 + 8;
                     let mut i = rng.gen_range(0, dice);
@@ -292,10 +327,14 @@ fn main() {
                             if enemy_count < 128 { if player.score < 1024 { 64 } else { 16 } } else { 0 },
                             if boss_count < 32 { 4 } else { 0 },
                             if shielded_boss_count < 32 { std::cmp::min(4, player.difficulty_level()) } else { 0 },
-                            if spiral_count < 4 { 4 } else { 0 }];
+                            if spiral_count < 4 { 4 } else { 0 },
+                            if turret_count < 8 { 4 } else { 0 },
+                            if kamikaze_count < 16 { std::cmp::min(8, 2 * player.difficulty_level()) } else { 0 },
+                            if splitter_count < 8 { 4 } else { 0 },
+                            if carrier_count < 2 { std::cmp::min(2, player.difficulty_level()) } else { 0 }];
                         let allweights = weights.iter().fold(0, |sum, x| sum + x);
                         let accum = {
-                            let mut accum = [0; 4];
+                            let mut accum = [0; 8];
                             let mut accumulator = 0;
                             for (i,e) in weights.iter().enumerate() {
                                 accumulator += e;
@@ -319,16 +358,26 @@ fn main() {
                                 _ => panic!("RNG returned out of range")
                             };
                             if let Some(x) = accum.iter().position(|x| dice < *x) {
+                                // Bosses come in to a halt for a while, spirals circle around the spawn side
+                                let stop = [rng.gen_range(64., WIDTH as f64 - 64.), rng.gen_range(64., HEIGHT as f64 / 3.)];
+                                let boss_movement = Movement::new(Pattern::SwoopIn{stop}).retreat_after(rng.gen_range(600, 1200));
                                 enemies.push(match x {
-                                    0 => Enemy::Enemy1(EnemyBase::new(&mut id_gen, pos, velo)
-                                        .health(3)),
-                                    1 => Enemy::Boss(EnemyBase::new(&mut id_gen, pos, velo)
-                                        .health(64)),
+                                    0 => Enemy::new_enemy1(&mut id_gen, pos, velo)
+                                        .with_movement(wave_movement(time / wave_period, pos, velo, &mut rng)),
+                                    1 => Enemy::new_boss(&mut id_gen, pos, velo).with_movement(boss_movement),
                                     2 => Enemy::ShieldedBoss(ShieldedBoss::new(
                                             &mut id_gen,
                                             pos,
-                                            velo)),
-                                    _ => Enemy::new_spiral(&mut id_gen, pos, velo),
+                                            velo)).with_movement(boss_movement),
+                                    3 => Enemy::new_spiral(&mut id_gen, pos, velo).with_movement(Movement::new(Pattern::Orbit{
+                                            center: [WIDTH as f64 / 2., HEIGHT as f64 / 3.],
+                                            radius: rng.gen_range(64., 160.),
+                                            angular_speed: if rng.gen() { 0.01 } else { -0.01 }}).retreat_after(900)),
+                                    4 => Enemy::new_turret(&mut id_gen, pos, velo).with_movement(boss_movement),
+                                    5 => Enemy::new_kamikaze(&mut id_gen, pos, velo),
+                                    6 => Enemy::new_splitter(&mut id_gen, pos, velo)
+                                        .with_movement(wave_movement(time / wave_period, pos, velo, &mut rng)),
+                                    _ => Enemy::new_carrier(&mut id_gen, pos, velo).with_movement(boss_movement),
                                 });
                             }
                         }
@@ -337,21 +386,39 @@ fn main() {
                 }
             }
 
+            // Set by anything that hits the player in this frame, which is handled after the enemies and bullets
+            let mut player_hit = false;
+            let mut spawned = Vec::<Enemy>::new();
+            let mut boss_defeated = false;
+            let level = player.difficulty_level();
             for (i, enemy) in &mut ((&mut enemies).iter_mut().enumerate()) {
                 if !paused {
+                    spawned.extend(enemy.launch_escorts(&mut id_gen));
                     let killed = {
-                        if let Some(death_reason) = enemy.animate(&mut id_gen, &mut bullets, &mut rng, time) {
+                        if let Some(death_reason) = enemy.animate(&mut id_gen, &mut bullets, &mut rng, time, &player.base, player.is_alive(), level) {
                             to_delete.push(i);
+                            if let DeathReason::HitPlayer = death_reason {
+                                add_tent(false, &enemy.get_base().pos, &mut id_gen, &mut rng);
+                                player_hit = true;
+                            }
                             if let DeathReason::Killed = death_reason {true} else{false}
                         }
                         else {false}
                     };
                     if killed {
-                        player.kills += 1;
-                        player.score += if enemy.is_boss() { 10 } else { 1 };
-                        if rng.gen_range(0, 100) < 20 {
-                            let ent = Entity::new(&mut id_gen, enemy.get_base().pos, [0., 1.]);
-                            items.push(enemy.drop_item(ent));
+                        let awarded = player.add_kill(enemy.score());
+                        popups.push(ScorePopup::new(enemy.get_base().pos, awarded, player.multiplier()));
+                        if let Some(item) = enemy.drop_item(&mut id_gen, &mut rng) {
+                            items.push(item);
+                        }
+                        spawned.extend(enemy.split(&mut id_gen));
+                        if let Some(boss) = enemy.as_stage_boss() {
+                            let bonus = time_bonus(time - boss.arrival);
+                            player.score += bonus;
+                            let pos = enemy.get_base().pos;
+                            popups.push(ScorePopup::new_text([pos[0], pos[1] + 16.],
+                                format!("TIME BONUS +{}", bonus), [1., 1., 0.5]));
+                            boss_defeated = true;
                         }
                         continue;
                     }
@@ -362,15 +429,32 @@ fn main() {
 
             for i in to_delete.iter().rev() {
                 let dead = enemies.remove(*i);
+                reservations.release_enemy(dead.get_id());
                 println!("Deleted Enemy {} id={}: {} / {}", match dead {
                     Enemy::Enemy1(_) => "enemy",
                     Enemy::Boss(_) => "boss",
                     Enemy::ShieldedBoss(_) => "ShieldedBoss",
                     Enemy::SpiralEnemy(_) => "SpiralEnemy",
+                    Enemy::Turret(_) => "Turret",
+                    Enemy::Kamikaze(_) => "Kamikaze",
+                    Enemy::Splitter(_) => "Splitter",
+                    Enemy::Carrier(_) => "Carrier",
+                    Enemy::StageBoss(_) => "StageBoss",
                 }, dead.get_id(), *i, enemies.len());
             }
 
             to_delete.clear();
+            enemies.extend(spawned);
+
+            if boss_defeated {
+                for pos in cancel_enemy_bullets(&mut bullets, |_| true) {
+                    add_tent(true, &pos, &mut id_gen, &mut rng);
+                }
+            }
+
+            if !paused {
+                reservations.update(&mut bullets, &player.locks, &enemies, &player.base);
+            }
 
             let mut bullets_to_delete: Vec<u32> = Vec::new();
             for (i,b) in &mut bullets.iter_mut() {
@@ -382,21 +466,26 @@ fn main() {
 
                         match death_reason {
                             DeathReason::Killed | DeathReason::HitPlayer =>
-                                add_tent(if let Projectile::Missile{..} = b { false } else { true }, &base.0.pos, &mut id_gen, &mut rng),
+                                add_tent(if let Projectile::Missile{..} = b { false } else { true }, &base.ent.pos, &mut id_gen, &mut rng),
                             _ => {}
                         }
 
-                        if let DeathReason::HitPlayer = death_reason {
-                            if player.invtime == 0 && !game_over && 0 < player.lives {
-                                player.lives -= 1;
-                                if player.lives == 0 {
-                                    game_over = true;
-                                }
-                                else{
-                                    player.invtime = PLAYER_INVINCIBLE_TIME;
-                                }
+                        if let &mut Projectile::Laser{killed: true, volley, ..} = b {
+                            // Bonus for kills from a volley of multiple locks
+                            if 1 < volley {
+                                let bonus = LOCK_BONUS_SCORE * volley * player.multiplier();
+                                player.score += bonus;
+                                popups.push(ScorePopup::new_text(base.ent.pos, format!("LOCK x{} +{}", volley, bonus), [1., 0.5, 0.75]));
                             }
                         }
+
+                        if let DeathReason::HitPlayer = death_reason {
+                            player_hit = true;
+                        }
+                    }
+                    else if !game_over && player.is_alive() && b.test_graze(&player.base) {
+                        player.graze += 1;
+                        player.score += GRAZE_SCORE;
                     }
                 }
 
@@ -405,7 +494,8 @@ fn main() {
 
             for i in bullets_to_delete.iter() {
                 if let Some(b) = bullets.remove(i) {
-                    println!("Deleted {} id={}, {} / {}", b.get_type(), b.get_base().0.id, *i, bullets.len());
+                    reservations.release_shot(*i);
+                    println!("Deleted {} id={}, {} / {}", b.get_type(), b.get_base().ent.id, *i, bullets.len());
                 }
                 else{
                     debug_assert!(false, "All keys must exist in bullets");
@@ -414,6 +504,30 @@ fn main() {
 
             bullets_to_delete.clear();
 
+            if player_hit && player.is_vulnerable() && !game_over && 0 < player.lives {
+                for _ in 0..8 {
+                    let pos = [
+                        player.base.pos[0] + 24. * (rng.gen::<f64>() - 0.5),
+                        player.base.pos[1] + 24. * (rng.gen::<f64>() - 0.5)
+                    ];
+                    add_tent(false, &pos, &mut id_gen, &mut rng);
+                }
+                let pos = player.base.pos;
+                cancel_locks(&mut player, &mut reservations);
+                let lost_power = player.kill();
+                items.extend(Item::scatter(&mut id_gen, pos, lost_power));
+            }
+
+            if 0 < player.bomb_time {
+                // Full screen flash and a shock wave expanding from the player
+                let f = player.bomb_time as f32 / BOMB_TIME as f32;
+                rectangle([1., 1., 1., 0.5 * f * f], [0., 0., WIDTH as f64, HEIGHT as f64], context.transform, graphics);
+                let radius = (BOMB_TIME - player.bomb_time) as f64 * WIDTH as f64 / BOMB_TIME as f64;
+                let pos = player.base.pos;
+                circle_arc([0.5, 0.75, 1., f], 4., 0., 2. * std::f64::consts::PI - 0.01,
+                    [pos[0] - radius, pos[1] - radius, radius * 2., radius * 2.], context.transform, graphics);
+            }
+
             for (i, e) in &mut ((&mut tent).iter_mut().enumerate()) {
                 if !paused {
                     if let Some(_) = e.animate_temp() {
@@ -428,13 +542,49 @@ fn main() {
                 tent.remove(*i);
                 //println!("Deleted tent {} / {}", *i, bullets.len());
             }
+            to_delete.clear();
+
+            if !paused {
+                for (i, popup) in &mut ((&mut popups).iter_mut().enumerate()) {
+                    if let Some(_) = popup.animate() {
+                        to_delete.push(i);
+                    }
+                }
+                for i in to_delete.iter().rev() {
+                    popups.remove(*i);
+                }
+            }
 
             // Right side bar
             rectangle([0.20, 0.20, 0.4, 1.],
                 [WIDTH as f64, 0., (WINDOW_WIDTH - WIDTH) as f64, WINDOW_HEIGHT as f64],
                 context.transform, graphics);
 
-            rectangle([0., 0.5, 0.4, 1.], [WIDTH as f64, (3) as f64 * 12.0 + 4., player.power as f64, 8.], context.transform, graphics);
+            rectangle([0., 0.5, 0.4, 1.], [WIDTH as f64, (3) as f64 * 12.0 + 4.,
+                player.power() as f64 * (WINDOW_WIDTH - WIDTH) as f64 / player.get_weapon().max_power() as f64, 8.], context.transform, graphics);
+
+            // Weapon energy right below the power, blinking red while overheated
+            if !player.overheat || disptime % 16 < 8 {
+                let color = if player.overheat { [1., 0.2, 0.2, 1.] } else { [0.2, 0.8, 1., 1.] };
+                rectangle(color, [WIDTH as f64, (3) as f64 * 12.0 + 12.,
+                    player.energy as f64 * (WINDOW_WIDTH - WIDTH) as f64 / WEAPON_ENERGY_MAX as f64, 3.], context.transform, graphics);
+            }
+
+            // Remaining time of the combo window
+            rectangle([0.5, 0.5, 0., 1.], [WIDTH as f64, (8) as f64 * 12.0 + 4.,
+                player.combo_time as f64 * (WINDOW_WIDTH - WIDTH) as f64 / COMBO_WINDOW as f64, 8.], context.transform, graphics);
+
+            // Boss health bar at the top of the playfield, with ticks where the phases change
+            let stage_boss = enemies.iter().find_map(|e| e.as_stage_boss());
+            if let Some(boss) = stage_boss {
+                let (x, y, w) = (8., 16., WIDTH as f64 - 16.);
+                let ratio = std::cmp::max(0, boss.health()) as f64 / boss.kind.health as f64;
+                rectangle([0.25, 0., 0., 0.75], [x, y, w, 6.], context.transform, graphics);
+                rectangle([1., 0.25, 0.25, 1.], [x, y, w * ratio, 6.], context.transform, graphics);
+                for phase in &boss.kind.phases[1..] {
+                    rectangle([1., 1., 1., 1.], [x + w * phase.threshold as f64 / 100. - 0.5, y - 2., 1., 10.], context.transform, graphics);
+                }
+            }
 
             let mut draw_text_pos = |s: &str, pos: [f64; 2], color: [f32; 4], size: u32| {
                 text::Text::new_color(color, size).draw(
@@ -446,6 +596,19 @@ fn main() {
                 ).unwrap_or_default();
             };
 
+            if let Some(boss) = stage_boss {
+                draw_text_pos(boss.kind.name, [8., 12.], [1., 0.75, 0.75, 1.], 12);
+                draw_text_pos(&format!("Bonus {}", time_bonus(time - boss.arrival)), [WIDTH as f64 - 80., 12.], [1., 1., 0.5, 1.], 12);
+            }
+
+            for popup in &popups {
+                draw_text_pos(&popup.text, [popup.pos[0] - 4. * popup.text.len() as f64, popup.pos[1]], popup.color(), 10);
+            }
+
+            if 0 < player.extend_time && disptime % 16 < 12 {
+                draw_text_pos("EXTEND!", [(WIDTH / 2 - 40) as f64, (HEIGHT / 3) as f64], [0.5, 1.0, 0.5, 1.0], 20);
+            }
+
             if paused {
                 draw_text_pos("PAUSED", [(WIDTH / 2 - 80) as f64, (HEIGHT / 2) as f64], [1.0, 1.0, 0.0, 1.0], 20);
             }
@@ -461,39 +624,51 @@ fn main() {
             draw_text(&format!("Frame: {}", time), 0);
             draw_text(&format!("Score: {}", player.score), 1);
             draw_text(&format!("Kills: {}", player.kills), 2);
-            draw_text(&format!("Power: {}, Level: {}", player.power, player.power_level()), 3);
+            draw_text(&format!("Power: {}, Level: {}/{}", player.power(), player.power_level(), player.get_weapon().max_level()), 3);
             draw_text(&format!("Wave: {} Level: {}", time / wave_period, player.difficulty_level()), 4);
-            draw_text(&format!("shots_bullet: {}", shots_bullet), 5);
-            draw_text(&format!("shots_missile: {}", shots_missile), 6);
-
-            let weapon_set = [(0, Weapon::Bullet, [1.,0.5,0.]), (2, Weapon::Light, [1.,1.,1.]), (3, Weapon::Missile, [0.,1.,0.]),
-                (4, Weapon::Lightning, [1., 1., 0.])];
+            draw_text(&format!("Shots: {} {}", player.get_weapon().name(), shots[player.weapon]), 5);
+            draw_text(&format!("Total shots: {}", shots.iter().sum::<u32>()), 6);
+            draw_text(&format!("Graze: {}", player.graze), 7);
+            draw_text(&format!("Combo: {} x{}", player.combo, player.multiplier()), 8);
+            draw_text(&format!("Medal: {}", player.medal_score()), 9);
 
+            // Key hints above both ends of the weapon row, which spans the whole sidebar
             draw_text_pos("Z", [
-                ((WINDOW_WIDTH + WIDTH) / 2 - weapon_set.len() as u32 * 32 / 2 - 16) as f64,
-                (WINDOW_HEIGHT * 3 / 4) as f64],
+                ((WINDOW_WIDTH + WIDTH) / 2 - WEAPONS.len() as u32 * 32 / 2 + 4) as f64,
+                (WINDOW_HEIGHT * 3 / 4 - 20) as f64],
                 [1.0, 1.0, 0.0, 1.0], 14);
             draw_text_pos("X", [
-                ((WINDOW_WIDTH + WIDTH) / 2 + weapon_set.len() as u32 * 32 / 2 + 6) as f64,
-                (WINDOW_HEIGHT * 3 / 4) as f64],
+                ((WINDOW_WIDTH + WIDTH) / 2 + WEAPONS.len() as u32 * 32 / 2 - 14) as f64,
+                (WINDOW_HEIGHT * 3 / 4 - 20) as f64],
                 [1.0, 1.0, 0.0, 1.0], 14);
 
             // Display weapon selection
             use piston_window::math::translate;
-            let centerize = translate([-((assets.sphere_tex.get_width() * weapon_set.len() as u32) as f64 / 2.), -(assets.sphere_tex.get_height() as f64 / 2.)]);
-            for (i,v) in weapon_set.iter().enumerate() {
-                let sphere_image = if v.1 == weapon {
-                    Image::new_color([v.2[0], v.2[1], v.2[2], 1.])
+            let centerize = translate([-((assets.sphere_tex.get_width() * WEAPONS.len() as u32) as f64 / 2.), -(assets.sphere_tex.get_height() as f64 / 2.)]);
+            for (i, v) in WEAPONS.iter().enumerate() {
+                let color = v.color();
+                let sphere_image = if i == player.weapon {
+                    Image::new_color([color[0], color[1], color[2], 1.])
                 }
                 else {
-                    Image::new_color([0.5 * v.2[0], 0.5 * v.2[1], 0.5 * v.2[2], 1.])
+                    Image::new_color([0.5 * color[0], 0.5 * color[1], 0.5 * color[2], 1.])
                 };
                 let transl = translate([((WINDOW_WIDTH + WIDTH) / 2 + i as u32 * 32) as f64, (WINDOW_HEIGHT * 3 / 4) as f64]);
                 let transform = (Matrix(context.transform) * Matrix(transl) * Matrix(centerize)).0;
                 sphere_image.draw(&assets.sphere_tex, &context.draw_state, transform, graphics);
-                let weapons_image = sphere_image.color(if v.1 == weapon { [1., 1., 1., 1.] } else { [0.5, 0.5, 0.5, 1.] })
-                .src_rect([v.0 as f64 * 32., 0., 32., assets.weapons_tex.get_height() as f64]);
+                let weapons_image = sphere_image.color(if i == player.weapon { [1., 1., 1., 1.] } else { [0.5, 0.5, 0.5, 1.] })
+                .src_rect([v.icon() as f64 * 32., 0., 32., assets.weapons_tex.get_height() as f64]);
                 weapons_image.draw(&assets.weapons_tex, &context.draw_state, transform, graphics);
+
+                // Level gauge of each weapon below its icon
+                let max_level = v.max_level();
+                let gauge_width = 24.;
+                let x = ((WINDOW_WIDTH + WIDTH) / 2 + i as u32 * 32) as f64 - (WEAPONS.len() * 32 / 2) as f64 + 4.;
+                let y = (WINDOW_HEIGHT * 3 / 4 + 18) as f64;
+                rectangle([0.1, 0.1, 0.1, 1.], [x, y, gauge_width, 4.], context.transform, graphics);
+                let level = player.weapon_level(i);
+                let color = if level == max_level { [1., 1., 0., 1.] } else { [color[0], color[1], color[2], 1.] };
+                rectangle(color, [x, y, gauge_width * level as f64 / max_level as f64, 4.], context.transform, graphics);
             }
 
             // Display player lives
@@ -505,6 +680,16 @@ fn main() {
                 image(&assets.player_tex, transform, graphics);
             }
 
+            // Display bomb stock above the lives
+            for i in 0..player.bombs {
+                let width = assets.bomb_tex.get_width();
+                let height = assets.bomb_tex.get_height();
+                let transl = translate([(WINDOW_WIDTH - (i + 1) as u32 * width) as f64,
+                    (WINDOW_HEIGHT - assets.player_tex.get_height() - height) as f64]);
+                let transform = (Matrix(context.transform) * Matrix(transl)).0;
+                image(&assets.bomb_tex, transform, graphics);
+            }
+
         });
         }
         // else if let Some(pos) = event.mouse_cursor_args() {
@@ -518,19 +703,24 @@ fn main() {
                         Key::Down | Key::S => key_down = tf,
                         Key::Left | Key::A => key_left = tf,
                         Key::Right | Key::D => key_right = tf,
-                        Key::C => key_shoot = tf,
+                        Key::C => {
+                            if key_shoot && !tf {
+                                shoot_released = true;
+                            }
+                            key_shoot = tf;
+                        },
+                        Key::LShift | Key::RShift => key_focus = tf,
+                        Key::V => {
+                            if !key_bomb && tf && !game_over && !paused && player.can_control() {
+                                player.use_bomb();
+                            }
+                            key_bomb = tf;
+                        },
                         Key::Z | Key::X => {
                             if !key_change && tf && !game_over {
-                                use Weapon::*;
-                                let weapon_set = [("Bullet", Bullet), ("Light", Light), ("Missile", Missile), ("Lightning", Lightning)];
-                                let (name, next_weapon) = match weapon {
-                                    Bullet => if key == Key::X { &weapon_set[1] } else { &weapon_set[3] },
-                                    Light => if key == Key::X { &weapon_set[2] } else { &weapon_set[0] },
-                                    Missile => if key == Key::X { &weapon_set[3] } else { &weapon_set[1] },
-                                    Lightning => if key == Key::X { &weapon_set[0] } else { &weapon_set[2] },
-                                };
-                                weapon = next_weapon.clone();
-                                println!("Weapon switched: {}", name);
+                                cancel_locks(&mut player, &mut reservations);
+                                player.switch_weapon(key == Key::X);
+                                println!("Weapon switched: {}", player.get_weapon().name());
                             }
                             key_change = tf;
                         },
@@ -544,12 +734,14 @@ fn main() {
                             items.clear();
                             enemies.clear();
                             bullets.clear();
+                            reservations.clear();
                             tent.clear();
+                            popups.clear();
+                            shoot_released = false;
                             time = 0;
                             id_gen = 0;
                             player.reset();
-                            shots_bullet = 0;
-                            shots_missile = 0;
+                            shots = vec![0; WEAPONS.len()];
                             paused = false;
                             game_over = false;
                         },
@@ -558,7 +750,7 @@ fn main() {
                                 player.score += 1000;
                         },
                         Key::H => if cfg!(debug_assertions) && tf {
-                            player.power += 16;
+                            player.add_power(WEAPON_POWER_PER_LEVEL);
                         },
                         _ => {}
                     }
@@ -0,0 +1,281 @@
+use rand::prelude::*;
+use vecmath::*;
+
+use super::consts::*;
+use super::entity::Entity;
+
+/// Fastest an enemy moves when catching up with its pattern.
+const MOVE_MAX_SPEED: f64 = 3.;
+/// Fraction of the remaining distance covered per frame while swooping in.
+const SWOOP_RATE: f64 = 0.04;
+const FOLLOW_STEERING: f64 = 0.02;
+const RETREAT_SPEED: f64 = 2.;
+const RETREAT_STEERING: f64 = 0.05;
+
+/// Shape of the flight of an enemy.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern{
+    /// Keeps the spawn velocity
+    Straight,
+    /// Flies along a spline through the waypoints, then keeps going in the last direction
+    Path{points: Vec<[f64; 2]>, speed: f64, progress: f64},
+    /// Sways up to `amplitude` pixels sideways of `velo` and back every `period` frames,
+    /// on the side toward the middle of the screen
+    Weave{velo: [f64; 2], amplitude: f64, period: f64},
+    /// Flies in decelerating and hovers at `stop`
+    SwoopIn{stop: [f64; 2]},
+    /// Circles around `center`, clockwise for a positive `angular_speed` in radians per frame
+    Orbit{center: [f64; 2], radius: f64, angular_speed: f64},
+    /// Homes in on the player
+    Follow{speed: f64},
+}
+
+/// Movement component of an enemy, which sets its velocity every frame before
+/// it's animated.  Any pattern can be cut short by leaving upward off-screen
+/// after `retreat_after` frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Movement{
+    pub pattern: Pattern,
+    /// frames since spawn
+    pub age: u32,
+    pub retreat_after: Option<u32>,
+}
+
+impl Default for Movement{
+    fn default() -> Self{
+        Self::new(Pattern::Straight)
+    }
+}
+
+impl Movement{
+    pub fn new(pattern: Pattern) -> Self{
+        Self{pattern, age: 0, retreat_after: None}
+    }
+
+    pub fn path(points: Vec<[f64; 2]>, speed: f64) -> Self{
+        Self::new(Pattern::Path{points, speed, progress: 0.})
+    }
+
+    pub fn retreat_after(mut self, frames: u32) -> Self{
+        self.retreat_after = Some(frames);
+        self
+    }
+
+    pub fn is_retreating(&self) -> bool{
+        self.retreat_after.map_or(false, |frames| frames <= self.age)
+    }
+
+    /// Sets the velocity of the entity for this frame.
+    pub fn update(&mut self, ent: &mut Entity, player: &Entity){
+        self.age += 1;
+        if self.is_retreating() {
+            ent.velo = vec2_add(ent.velo, vec2_scale(vec2_sub([0., -RETREAT_SPEED], ent.velo), RETREAT_STEERING));
+            return;
+        }
+        let pos = ent.pos;
+        match &mut self.pattern {
+            Pattern::Straight => (),
+            Pattern::Path{points, speed, progress} => {
+                // Paths without a segment to follow keep the spawn velocity
+                let last = points.len().saturating_sub(1) as f64;
+                if *progress < last {
+                    // Advance by the speed, measured along the chord of the current segment
+                    let i = *progress as usize;
+                    let chord = vec2_len(vec2_sub(points[i + 1], points[i])).max(1.);
+                    *progress = (*progress + *speed / chord).min(last);
+                    if let Some(target) = catmull_rom(points, *progress) {
+                        ent.velo = clamp_speed(vec2_sub(target, pos), MOVE_MAX_SPEED);
+                    }
+                }
+            }
+            Pattern::Weave{velo, amplitude, period} => {
+                use std::f64::consts::PI;
+                let len = vec2_len(*velo);
+                let side = if len < std::f64::EPSILON { [1., 0.] } else { [-velo[1] / len, velo[0] / len] };
+                // Only swing inward, or enemies spawned at the edge would leave the screen at once
+                if self.age == 1 && side[0] * (WIDTH as f64 / 2. - pos[0]) < 0. {
+                    *amplitude = -*amplitude;
+                }
+                // Move by the change of the offset from the spawn line, which starts and ends at rest
+                let omega = 2. * PI / *period;
+                let offset = |age: u32| *amplitude * (1. - (omega * age as f64).cos()) / 2.;
+                let sway = offset(self.age) - offset(self.age - 1);
+                ent.velo = vec2_add(*velo, vec2_scale(side, sway));
+            }
+            Pattern::SwoopIn{stop} => {
+                ent.velo = clamp_speed(vec2_scale(vec2_sub(*stop, pos), SWOOP_RATE), MOVE_MAX_SPEED);
+            }
+            Pattern::Orbit{center, radius, angular_speed} => {
+                let delta = vec2_sub(pos, *center);
+                let angle = delta[1].atan2(delta[0]) + *angular_speed;
+                let target = vec2_add(*center, vec2_scale([angle.cos(), angle.sin()], *radius));
+                ent.velo = clamp_speed(vec2_sub(target, pos), MOVE_MAX_SPEED);
+            }
+            Pattern::Follow{speed} => {
+                let delta = vec2_sub(player.pos, pos);
+                if std::f64::EPSILON < vec2_square_len(delta) {
+                    let desired = vec2_scale(vec2_normalized(delta), *speed);
+                    ent.velo = vec2_add(ent.velo, vec2_scale(vec2_sub(desired, ent.velo), FOLLOW_STEERING));
+                }
+            }
+        }
+    }
+}
+
+fn clamp_speed(velo: [f64; 2], max: f64) -> [f64; 2]{
+    let len = vec2_len(velo);
+    if max < len { vec2_scale(velo, max / len) } else { velo }
+}
+
+/// Point on the Catmull-Rom spline through `points` at `t`, where integer values
+/// of `t` are at the points themselves.  The end points are repeated so that
+/// the curve starts and ends on them.  Returns None if there are no points.
+pub fn catmull_rom(points: &[[f64; 2]], t: f64) -> Option<[f64; 2]>{
+    let last = points.len().checked_sub(1)?;
+    let i = std::cmp::min(t.max(0.) as usize, last.saturating_sub(1));
+    let get = |j: isize| points[std::cmp::min(std::cmp::max(j, 0) as usize, last)];
+    let (p0, p1, p2, p3) = (get(i as isize - 1), get(i as isize), get(i as isize + 1), get(i as isize + 2));
+    let s = t - i as f64;
+    let (s2, s3) = (s * s, s * s * s);
+    let mut ret = [0.; 2];
+    for k in 0..2 {
+        ret[k] = 0.5 * (2. * p1[k]
+            + (-p0[k] + p2[k]) * s
+            + (2. * p0[k] - 5. * p1[k] + 4. * p2[k] - p3[k]) * s2
+            + (-p0[k] + 3. * p1[k] - 3. * p2[k] + p3[k]) * s3);
+    }
+    Some(ret)
+}
+
+/// Choreography shared by the small enemies of a wave, so that each wave has
+/// its own recognisable flight pattern.
+pub fn wave_movement(wave: u32, pos: [f64; 2], velo: [f64; 2], rng: &mut ThreadRng) -> Movement{
+    let (w, h) = (WIDTH as f64, HEIGHT as f64);
+    match wave % 5 {
+        0 => Movement::new(Pattern::Straight),
+        1 => Movement::new(Pattern::Weave{velo: [velo[0] * 0.5, velo[1].abs().max(0.5)], amplitude: 32., period: 120.}),
+        2 => Movement::new(Pattern::SwoopIn{stop: [pos[0].max(32.).min(w - 32.), rng.gen_range(48., h / 3.)]})
+            .retreat_after(rng.gen_range(240, 480)),
+        // S-curve across the screen and down
+        3 => Movement::path(vec![pos, [w / 2., h / 4.], [w - pos[0], h / 2.], [pos[0], h + 32.]], 1.5),
+        _ => Movement::new(Pattern::Follow{speed: 1.}).retreat_after(600),
+    }
+}
+
+#[cfg(test)]
+fn simulate(ent: &mut Entity, movement: &mut Movement, player: &Entity, frames: u32){
+    for _ in 0..frames {
+        movement.update(ent, player);
+        for i in 0..2 {
+            ent.pos[i] += ent.velo[i];
+        }
+    }
+}
+
+#[test]
+fn test_weave() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [0., 0.], [0., 0.]);
+    let mut ent = Entity::new(&mut id_gen, [100., 0.], [0., 1.]);
+    let mut movement = Movement::new(Pattern::Weave{velo: [0., 1.], amplitude: 32., period: 100.});
+    let mut max_sway = 0f64;
+    for _ in 0..100 {
+        simulate(&mut ent, &mut movement, &player, 1);
+        max_sway = max_sway.max((ent.pos[0] - 100.).abs());
+    }
+    assert!(28. < max_sway && max_sway < 36.);
+    // Back on the center line after a whole period
+    assert!((ent.pos[0] - 100.).abs() < 4.);
+    assert!((ent.pos[1] - 100.).abs() < 1e-6);
+}
+
+#[test]
+fn test_swoop_in() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [0., 0.], [0., 0.]);
+    let mut ent = Entity::new(&mut id_gen, [100., 0.], [0., 1.]);
+    let mut movement = Movement::new(Pattern::SwoopIn{stop: [150., 120.]}).retreat_after(300);
+    simulate(&mut ent, &mut movement, &player, 200);
+    assert!(vec2_len(vec2_sub(ent.pos, [150., 120.])) < 1.);
+    assert!(vec2_len(ent.velo) < 0.1);
+
+    // Leaves upward after the timer
+    simulate(&mut ent, &mut movement, &player, 150);
+    assert!(movement.is_retreating());
+    assert!(ent.velo[1] < -1.);
+    assert!(ent.pos[1] < 120.);
+}
+
+#[test]
+fn test_orbit() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [0., 0.], [0., 0.]);
+    let mut ent = Entity::new(&mut id_gen, [150., 100.], [0., 0.]);
+    let mut movement = Movement::new(Pattern::Orbit{center: [100., 100.], radius: 50., angular_speed: 0.02});
+    for _ in 0..500 {
+        simulate(&mut ent, &mut movement, &player, 1);
+        assert!((vec2_len(vec2_sub(ent.pos, [100., 100.])) - 50.).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_follow() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [100., 400.], [0., 0.]);
+    let mut ent = Entity::new(&mut id_gen, [300., 0.], [0., 1.]);
+    let mut movement = Movement::new(Pattern::Follow{speed: 2.});
+    let dist = vec2_len(vec2_sub(player.pos, ent.pos));
+    simulate(&mut ent, &mut movement, &player, 100);
+    assert!(vec2_len(vec2_sub(player.pos, ent.pos)) < dist - 100.);
+    assert!(ent.velo[0] < 0.);
+}
+
+#[test]
+fn test_path() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [0., 0.], [0., 0.]);
+    let points = vec![[0., 0.], [100., 100.], [200., 0.], [300., 100.]];
+    assert_eq!(catmull_rom(&points, 1.), Some(points[1]));
+    assert_eq!(catmull_rom(&points, 3.), Some(points[3]));
+    assert_eq!(catmull_rom(&[], 0.), None);
+
+    let mut ent = Entity::new(&mut id_gen, points[0], [0., 0.]);
+    let mut movement = Movement::path(points.clone(), 2.);
+    let mut nearest = vec![std::f64::INFINITY; points.len()];
+    for _ in 0..300 {
+        simulate(&mut ent, &mut movement, &player, 1);
+        for (d, p) in nearest.iter_mut().zip(points.iter()) {
+            *d = d.min(vec2_len(vec2_sub(ent.pos, *p)));
+        }
+    }
+    assert!(nearest.iter().all(|d| *d < 2.));
+    // Keeps going after the last waypoint
+    assert!(300. < ent.pos[0]);
+
+    // Paths too short to follow fly straight
+    for points in vec![vec![], vec![[0., 0.]]] {
+        let mut ent = Entity::new(&mut id_gen, [0., 0.], [1., 0.]);
+        simulate(&mut ent, &mut Movement::path(points, 2.), &player, 10);
+        assert_eq!(ent.pos, [10., 0.]);
+    }
+}
+
+#[test]
+fn test_weave_from_edge() {
+    use super::entity::Enemy;
+    use std::collections::HashMap;
+    let mut id_gen = 0;
+    let mut rng = thread_rng();
+    let mut bullets = HashMap::new();
+    let player = Entity::new(&mut id_gen, [100., 400.], [0., 0.]);
+    for &(pos, velo) in &[([0., 100.], [0., 0.5]), ([WIDTH as f64, 100.], [0., -0.5])] {
+        let mut enemy = Enemy::new_enemy1(&mut id_gen, pos, velo)
+            .with_movement(wave_movement(1, pos, velo, &mut rng));
+        for _ in 0..120 {
+            assert!(enemy.animate(&mut id_gen, &mut bullets, &mut rng, 1, &player, true, 0).is_none());
+            bullets.clear();
+            let x = enemy.get_base().pos[0];
+            assert!(0. <= x && x <= WIDTH as f64);
+        }
+    }
+}
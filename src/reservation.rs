@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use vecmath::*;
+
+use super::entity::{Entity, Enemy, Projectile};
+
+const MISSILE_DETECTION_RANGE: f64 = 256.;
+/// Bosses are preferred as if they were this much closer to the missile.
+const MISSILE_BOSS_PRIORITY: f64 = 128.;
+/// Enemies within this distance from the player are preferred the closer they get.
+const MISSILE_THREAT_RANGE: f64 = 160.;
+
+/// Damage that homing shots are going to deal, so that a volley spreads over
+/// enemies instead of overkilling the same one.
+///
+/// A shot is a missile or laser in flight, or a lock of the lock-on weapon that
+/// is yet to be fired, and reserves at most one enemy.  The table is the only
+/// owner of reservations, and `update` drops every entry whose shot or enemy is
+/// gone, so the per-enemy totals can never drift from the shots.
+#[derive(Default)]
+pub struct Reservations{
+    /// Shot id -> (enemy id, reserved damage)
+    by_shot: HashMap<u32, (u32, i32)>,
+    /// Enemy id -> total damage reserved by all shots
+    by_enemy: HashMap<u32, i32>,
+}
+
+impl Reservations{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Damage reserved on an enemy by all shots.
+    pub fn reserved(&self, enemy: u32) -> i32{
+        *self.by_enemy.get(&enemy).unwrap_or(&0)
+    }
+
+    /// True if the shots aimed at the enemy are going to destroy it anyway.
+    pub fn is_doomed(&self, enemy: &Enemy) -> bool{
+        enemy.total_health() <= self.reserved(enemy.get_id())
+    }
+
+    /// Enemy id and damage reserved by a shot.
+    pub fn get(&self, shot: u32) -> Option<(u32, i32)>{
+        self.by_shot.get(&shot).cloned()
+    }
+
+    pub fn target_of(&self, shot: u32) -> Option<u32>{
+        self.get(shot).map(|(enemy, _)| enemy)
+    }
+
+    /// Reserves damage on an enemy for a shot, replacing its previous reservation.
+    pub fn reserve(&mut self, shot: u32, enemy: u32, damage: i32){
+        self.release_shot(shot);
+        self.by_shot.insert(shot, (enemy, damage));
+        *self.by_enemy.entry(enemy).or_insert(0) += damage;
+    }
+
+    /// Releases the reservation of a shot, returning the enemy it was aimed at.
+    pub fn release_shot(&mut self, shot: u32) -> Option<u32>{
+        let (enemy, damage) = self.by_shot.remove(&shot)?;
+        let remove = match self.by_enemy.get_mut(&enemy) {
+            Some(total) => {
+                *total -= damage;
+                *total == 0
+            }
+            None => {
+                debug_assert!(false, "Reserved enemy {} has no total", enemy);
+                false
+            }
+        };
+        if remove {
+            self.by_enemy.remove(&enemy);
+        }
+        Some(enemy)
+    }
+
+    /// Releases all reservations on an enemy, returning the shots that lost their target.
+    pub fn release_enemy(&mut self, enemy: u32) -> Vec<u32>{
+        let shots: Vec<u32> = self.by_shot.iter()
+            .filter(|(_, &(e, _))| e == enemy)
+            .map(|(&s, _)| s)
+            .collect();
+        for shot in &shots {
+            self.by_shot.remove(shot);
+        }
+        self.by_enemy.remove(&enemy);
+        shots
+    }
+
+    /// Must be called on game reset since ids start over from 0.
+    pub fn clear(&mut self){
+        self.by_shot.clear();
+        self.by_enemy.clear();
+    }
+
+    /// Drops reservations of shots that are gone from both `bullets` and `locks`
+    /// and of dead enemies, then picks targets for missiles that don't have one.
+    pub fn update(&mut self, bullets: &mut HashMap<u32, Projectile>, locks: &[u32], enemies: &[Enemy], player: &Entity){
+        let stale: Vec<u32> = self.by_shot.iter()
+            .filter(|(shot, &(enemy, _))| (!bullets.contains_key(shot) && !locks.contains(shot))
+                || enemies.iter().all(|e| e.get_id() != enemy))
+            .map(|(&s, _)| s)
+            .collect();
+        for shot in stale {
+            self.release_shot(shot);
+        }
+
+        for (&id, b) in bullets.iter_mut() {
+            if let Projectile::Missile{base, target, ..} = b {
+                if self.target_of(id) != Some(*target) {
+                    *target = 0;
+                }
+                if *target != 0 {
+                    continue;
+                }
+                if let Some(enemy) = self.pick_target(base.ent.pos, enemies, player) {
+                    *target = enemy;
+                    self.reserve(id, enemy, base.ent.health);
+                }
+            }
+        }
+    }
+
+    /// Finds the most threatening enemy in range that isn't going to die anyway.
+    fn pick_target(&self, pos: [f64; 2], enemies: &[Enemy], player: &Entity) -> Option<u32>{
+        enemies.iter()
+            .filter(|enemy| !self.is_doomed(enemy))
+            .filter_map(|enemy| {
+                let e = enemy.get_base();
+                let dist = vec2_len(vec2_sub(pos, e.pos));
+                if MISSILE_DETECTION_RANGE <= dist {
+                    return None;
+                }
+                let mut score = dist;
+                if enemy.is_boss() {
+                    score -= MISSILE_BOSS_PRIORITY;
+                }
+                let player_dist = vec2_len(vec2_sub(player.pos, e.pos));
+                if player_dist < MISSILE_THREAT_RANGE {
+                    score -= MISSILE_THREAT_RANGE - player_dist;
+                }
+                Some((e.id, score))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(id, _)| id)
+    }
+
+    /// Totals must always match the sum of individual reservations.
+    #[cfg(test)]
+    fn is_consistent(&self) -> bool{
+        let mut totals = HashMap::new();
+        for &(enemy, damage) in self.by_shot.values() {
+            *totals.entry(enemy).or_insert(0) += damage;
+        }
+        totals == self.by_enemy
+    }
+}
+
+#[cfg(test)]
+fn new_missile(id_gen: &mut u32, pos: [f64; 2]) -> Projectile{
+    use super::entity::BulletBase;
+    Projectile::new_missile(BulletBase::new(Entity::new(id_gen, pos, [0., -1.]).health(5)))
+}
+
+#[cfg(test)]
+fn target(bullets: &HashMap<u32, Projectile>, id: u32) -> u32{
+    match bullets[&id] {
+        Projectile::Missile{target, ..} => target,
+        _ => panic!("not a missile"),
+    }
+}
+
+#[test]
+fn test_reservation() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [100., 400.], [0., 0.]);
+    // Enemy1 has 3 health, so a single missile is enough for each
+    let mut enemies = vec![
+        Enemy::new_enemy1(&mut id_gen, [100., 100.], [0., 0.]),
+        Enemy::new_enemy1(&mut id_gen, [150., 100.], [0., 0.]),
+    ];
+    let mut bullets = HashMap::new();
+    for x in &[100., 110., 120.] {
+        let missile = new_missile(&mut id_gen, [*x, 200.]);
+        bullets.insert(missile.get_id(), missile);
+    }
+    let ids: Vec<u32> = { let mut ids: Vec<u32> = bullets.keys().cloned().collect(); ids.sort(); ids };
+    let mut res = Reservations::new();
+
+    res.update(&mut bullets, &[], &enemies, &player);
+    assert!(res.is_consistent());
+    assert_eq!(res.by_shot.len(), 2);
+    assert_eq!(res.reserved(enemies[0].get_id()), 5);
+    assert_eq!(res.reserved(enemies[1].get_id()), 5);
+    for &id in &ids {
+        let t = target(&bullets, id);
+        assert_eq!(res.target_of(id), if t == 0 { None } else { Some(t) });
+    }
+
+    // The target dies first; the reservation is freed and handed over
+    let dead = enemies.remove(0).get_id();
+    res.update(&mut bullets, &[], &enemies, &player);
+    assert!(res.is_consistent());
+    assert_eq!(res.reserved(dead), 0);
+    assert_eq!(res.reserved(enemies[0].get_id()), 5);
+    assert!(ids.iter().all(|&id| target(&bullets, id) != dead));
+
+    // The missile leaves the screen without hitting anything
+    let gone = res.by_shot.keys().next().cloned().unwrap();
+    bullets.remove(&gone);
+    res.update(&mut bullets, &[], &enemies, &player);
+    assert!(res.is_consistent());
+    assert_eq!(res.target_of(gone), None);
+    assert_eq!(res.reserved(enemies[0].get_id()), 5);
+
+    assert_eq!(res.release_enemy(enemies[0].get_id()).len(), 1);
+    assert!(res.is_consistent());
+    res.update(&mut bullets, &[], &enemies, &player);
+    assert_eq!(res.by_shot.len(), 1);
+
+    // Locks keep their reservation until they are fired or dropped
+    let lock = 1000;
+    res.reserve(lock, enemies[0].get_id(), 5);
+    res.update(&mut bullets, &[lock], &enemies, &player);
+    assert_eq!(res.get(lock), Some((enemies[0].get_id(), 5)));
+    assert!(res.is_doomed(&enemies[0]));
+    res.update(&mut bullets, &[], &enemies, &player);
+    assert_eq!(res.target_of(lock), None);
+    assert!(res.is_consistent());
+
+    res.clear();
+    assert_eq!(res.by_shot.len(), 0);
+    assert!(res.is_consistent());
+}
+
+#[test]
+fn test_threat_priority() {
+    let mut id_gen = 0;
+    let player = Entity::new(&mut id_gen, [300., 400.], [0., 0.]);
+    let enemies = vec![
+        Enemy::new_enemy1(&mut id_gen, [100., 100.], [0., 0.]),
+        Enemy::new_boss(&mut id_gen, [100., 0.], [0., 0.]),
+        Enemy::new_enemy1(&mut id_gen, [300., 350.], [0., 0.]),
+    ];
+    let res = Reservations::new();
+
+    // The boss is a bit farther than the small fry but preferred
+    assert_eq!(res.pick_target([100., 150.], &enemies, &player), Some(enemies[1].get_id()));
+
+    // An enemy closing in on the player beats a closer one
+    assert_eq!(res.pick_target([180., 200.], &enemies, &player), Some(enemies[2].get_id()));
+
+    // Nothing in range
+    assert_eq!(res.pick_target([600., 600.], &enemies[..2], &player), None);
+}
@@ -0,0 +1,817 @@
+use piston_window::*;
+use piston_window::draw_state::Blend;
+use rand::prelude::*;
+use std::collections::HashMap;
+use vecmath::*;
+
+use super::consts::*;
+use super::collision::{Shape, Collider};
+use super::entity::{Entity, Player, Enemy, BulletBase, Projectile};
+use super::reservation::Reservations;
+
+type LightningSeed = <SmallRng as SeedableRng>::Seed;
+
+/// Visual result of firing a weapon in a frame, rendered by Weapon::draw.
+pub enum WeaponEffect{
+    /// Light beam from `pos` up to `end`, with a flare at the end if it hit something
+    Beam{ pos: [f64; 2], width: f64, end: f64, impact: bool },
+    Lightning{ pos: [f64; 2], seed: LightningSeed, length: u32, hit: bool },
+    /// Hop of a chaining lightning from an enemy to the next
+    LightningArc{ from: [f64; 2], to: [f64; 2], seed: LightningSeed },
+    Charge{ pos: [f64; 2], charge: u32, blink: bool },
+    Reticle{ pos: [f64; 2] },
+    Lock{ pos: [f64; 2] },
+}
+
+/// Everything a weapon can touch while firing.
+pub struct FireContext<'a>{
+    pub player: &'a Player,
+    pub enemies: &'a mut Vec<Enemy>,
+    /// damage that homing shots and locks are going to deal
+    pub reservations: &'a mut Reservations,
+    pub bullets: &'a mut HashMap<u32, Projectile>,
+    pub id_gen: &'a mut u32,
+    pub rng: &'a mut ThreadRng,
+    pub time: u32,
+    /// where the shots come from, the player or one of the options
+    pub pos: [f64; 2],
+    /// positions where a hit spark should be shown
+    pub hits: Vec<[f64; 2]>,
+    pub effects: Vec<WeaponEffect>,
+}
+
+impl<'a> FireContext<'a>{
+    pub fn new(player: &'a Player, enemies: &'a mut Vec<Enemy>, reservations: &'a mut Reservations,
+        bullets: &'a mut HashMap<u32, Projectile>, id_gen: &'a mut u32, rng: &'a mut ThreadRng, time: u32) -> Self
+    {
+        let pos = player.base.pos;
+        Self{player, enemies, reservations, bullets, id_gen, rng, time, pos, hits: vec![], effects: vec![]}
+    }
+}
+
+pub trait Weapon: Sync{
+    fn name(&self) -> &'static str;
+
+    /// Index of the icon in weapons.png
+    fn icon(&self) -> u32;
+
+    /// Color of the sphere behind the icon in the weapon selector
+    fn color(&self) -> [f32; 3];
+
+    /// Highest level the weapon can be powered up to.
+    fn max_level(&self) -> u32;
+
+    fn max_power(&self) -> u32{
+        self.max_level() * WEAPON_POWER_PER_LEVEL
+    }
+
+    /// Frames to wait between shots, zero for weapons that fire every frame.
+    fn cooldown(&self) -> u32{
+        0
+    }
+
+    /// Energy drained for each frame of fire.
+    fn energy_cost(&self) -> u32{
+        0
+    }
+
+    /// Fires the weapon at the given level and returns the number of projectiles shot.
+    fn fire(&self, ctx: &mut FireContext, level: u32) -> u32;
+
+    /// Fires the reduced version of the weapon from an option at `ctx.pos`.
+    fn fire_option(&self, ctx: &mut FireContext, _level: u32) -> u32{
+        self.fire(ctx, 0)
+    }
+
+    /// True if holding the fire key charges a shot fired by `release`.
+    fn can_charge(&self) -> bool{
+        false
+    }
+
+    /// Called every frame the fire key is held after `charge` frames.
+    /// Returns ids of new locks, whose targets are reserved in `ctx.reservations`.
+    fn hold(&self, _ctx: &mut FireContext, _level: u32, _charge: u32) -> Vec<u32>{
+        vec![]
+    }
+
+    /// Fires the charged shot when the fire key is released after `charge` frames.
+    fn release(&self, _ctx: &mut FireContext, _level: u32, _charge: u32) -> u32{
+        0
+    }
+
+    fn draw(&self, _effects: &[WeaponEffect], _context: &Context, _graphics: &mut G2d){}
+}
+
+/// All weapons in the order of selection.  Adding one here is enough to make it
+/// selectable and shown in the sidebar.
+pub static WEAPONS: &[&dyn Weapon] = &[&BulletWeapon, &LightWeapon, &MissileWeapon, &LightningWeapon, &LockOnWeapon];
+
+/// Spawns a fan of `count` projectiles centered on the player's heading.
+fn fire_fan(ctx: &mut FireContext, count: i32, speed: f64, make: &dyn Fn(Entity) -> Projectile) -> u32{
+    let spread = if ctx.player.focus { PLAYER_FOCUS_SPREAD } else { 1. };
+    for i in 0..count {
+        let vx = (i as f64 - (count - 1) as f64 / 2.) * spread;
+        let ent = Entity::new(ctx.id_gen, ctx.pos, [vx, -speed])
+            .rotation((vx as f32).atan2(speed as f32));
+        let projectile = make(ent);
+        ctx.bullets.insert(projectile.get_base().ent.id, projectile);
+    }
+    count as u32
+}
+
+pub struct BulletWeapon;
+
+impl Weapon for BulletWeapon{
+    fn name(&self) -> &'static str { "Bullet" }
+    fn icon(&self) -> u32 { 0 }
+    fn color(&self) -> [f32; 3] { [1., 0.5, 0.] }
+    fn max_level(&self) -> u32 { 8 }
+    fn cooldown(&self) -> u32 { 5 }
+
+    fn fire(&self, ctx: &mut FireContext, level: u32) -> u32{
+        // Two more bullets per level
+        fire_fan(ctx, 3 + 2 * level as i32, BULLET_SPEED,
+            &|ent| Projectile::new_bullet(BulletBase::new(ent.blend(Blend::Add))))
+    }
+
+    fn fire_option(&self, ctx: &mut FireContext, _level: u32) -> u32{
+        fire_fan(ctx, 1, BULLET_SPEED,
+            &|ent| Projectile::new_bullet(BulletBase::new(ent.blend(Blend::Add))))
+    }
+
+    fn can_charge(&self) -> bool { true }
+
+    fn hold(&self, ctx: &mut FireContext, _level: u32, charge: u32) -> Vec<u32>{
+        ctx.effects.push(WeaponEffect::Charge{pos: ctx.pos, charge, blink: ctx.time % 8 < 4});
+        vec![]
+    }
+
+    fn release(&self, ctx: &mut FireContext, level: u32, charge: u32) -> u32{
+        if charge < CHARGE_THRESHOLD {
+            return 0;
+        }
+        // Both size and damage grow with the charge time and the power level
+        let charge = std::cmp::min(charge, CHARGE_MAX);
+        let radius = 4. + (charge / 16 + level) as f64;
+        let damage = (4 + charge / 8 + 2 * level) as i32;
+        let ent = Entity::new(ctx.id_gen, ctx.pos, [0., -CHARGE_SHOT_SPEED])
+            .health(damage)
+            .blend(Blend::Add);
+        let projectile = Projectile::new_charge_shot(BulletBase::new(ent), radius);
+        ctx.bullets.insert(projectile.get_id(), projectile);
+        1
+    }
+
+    fn draw(&self, effects: &[WeaponEffect], context: &Context, graphics: &mut G2d){
+        use std::f64::consts::{PI, FRAC_PI_2};
+        for effect in effects {
+            if let WeaponEffect::Charge{pos, charge, blink} = effect {
+                // Arc growing with the charge, turning into a flashing ring when ready
+                let r = PLAYER_SIZE + 4.;
+                let rect = [pos[0] - r, pos[1] - r, r * 2., r * 2.];
+                if CHARGE_THRESHOLD <= *charge {
+                    let bright = if *blink { 1. } else { 0.5 };
+                    let ratio = (*charge as f64 / CHARGE_MAX as f64).min(1.);
+                    circle_arc([bright, bright, 0.5, 1.], 1. + 2. * ratio, 0., 2. * PI, rect, context.transform, graphics);
+                }
+                else if 0 < *charge {
+                    let angle = 2. * PI * *charge as f64 / CHARGE_THRESHOLD as f64;
+                    circle_arc([0.5, 0.75, 1., 0.75], 1., -FRAC_PI_2, angle - FRAC_PI_2, rect, context.transform, graphics);
+                }
+            }
+        }
+    }
+}
+
+pub struct MissileWeapon;
+
+impl Weapon for MissileWeapon{
+    fn name(&self) -> &'static str { "Missile" }
+    fn icon(&self) -> u32 { 3 }
+    fn color(&self) -> [f32; 3] { [0., 1., 0.] }
+    fn max_level(&self) -> u32 { 5 }
+    fn cooldown(&self) -> u32 { 50 }
+
+    fn fire(&self, ctx: &mut FireContext, level: u32) -> u32{
+        // Missiles home in, so one more per level is enough
+        fire_fan(ctx, 3 + level as i32, MISSILE_SPEED,
+            &|ent| Projectile::new_missile(BulletBase::new(ent.health(5))))
+    }
+
+    fn fire_option(&self, ctx: &mut FireContext, _level: u32) -> u32{
+        fire_fan(ctx, 1, MISSILE_SPEED,
+            &|ent| Projectile::new_missile(BulletBase::new(ent.health(5))))
+    }
+}
+
+pub struct LightWeapon;
+
+impl Weapon for LightWeapon{
+    fn name(&self) -> &'static str { "Light" }
+    fn icon(&self) -> u32 { 2 }
+    fn color(&self) -> [f32; 3] { [1., 1., 1.] }
+    fn max_level(&self) -> u32 { 6 }
+    fn energy_cost(&self) -> u32 { 2 }
+
+    fn fire(&self, ctx: &mut FireContext, level: u32) -> u32{
+        let pos = ctx.pos;
+        let width = LIGHT_WIDTH + level as f64;
+        let beam = Collider::aabb([pos[0] - width, 0., pos[0] + width, pos[1]]);
+
+        // Enemies in the way from the nearest, with the bottom edge where the beam hits
+        let mut targets: Vec<(f64, &mut Enemy)> = ctx.enemies.iter_mut()
+            .filter(|e| e.hits(&beam))
+            .map(|e| (e.collider().bounding_box()[3].min(pos[1]), e))
+            .collect();
+        targets.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        // Higher levels pass through more enemies, but never through a shield
+        let mut pierce = level / LIGHT_PIERCE_LEVELS;
+        let mut end = 0.;
+        let mut impact = false;
+        for (hit_y, enemy) in targets {
+            ctx.hits.push([pos[0], hit_y]);
+            enemy.damage(1 + level as i32);
+            if pierce == 0 || enemy.is_shielded() {
+                end = hit_y;
+                impact = true;
+                break;
+            }
+            pierce -= 1;
+        }
+        ctx.effects.push(WeaponEffect::Beam{pos, width, end, impact});
+        0
+    }
+
+    fn draw(&self, effects: &[WeaponEffect], context: &Context, graphics: &mut G2d){
+        for effect in effects {
+            if let WeaponEffect::Beam{pos, width, end, impact} = effect {
+                // Apparently Piston doesn't allow vertex colored rectangle, we need to
+                // draw multiple lines in order to display gradual change in color.
+                let half = *width as i32;
+                for i in -half..half + 1 {
+                    let f = (half as f32 + 1. - i.abs() as f32) / (half as f32 + 1.);
+                    line([f / 3., 0.5 + f / 2., 1., f],
+                        1.,
+                        [pos[0] + i as f64, pos[1], pos[0] + i as f64, *end],
+                        context.transform, graphics);
+                }
+                if *impact {
+                    let r = *width * 2.;
+                    ellipse([0.75, 0.9, 1., 0.75], [pos[0] - r, end - r / 2., r * 2., r], context.transform, graphics);
+                }
+            }
+        }
+    }
+}
+
+pub struct LightningWeapon;
+
+impl LightningWeapon{
+    /// Random walk with momentum
+    fn next_lightning(rng: &mut SmallRng, a: &mut [f64; 4]){
+        a[2] += LIGHTNING_ACCEL * (rng.gen::<f64>() - 0.5) - a[2] * LIGHTNING_FEEDBACK;
+        a[3] += LIGHTNING_ACCEL * (rng.gen::<f64>() - 0.5) - a[3] * LIGHTNING_FEEDBACK;
+        a[0] += a[2];
+        a[1] += a[3];
+    }
+
+    /// Calls the same lightning sequence for the same seed, first pass for detecting
+    /// hit enemy and second pass for rendering.  Stops when `f` returns false and
+    /// returns the number of segments walked.
+    fn walk(pos: [f64; 2], seed: &LightningSeed, length: u32, f: &mut dyn FnMut(&[f64; 4]) -> bool) -> u32{
+        let mut rng2 = SmallRng::from_seed(*seed);
+        let mut a = [pos[0], pos[1], 0., -16.];
+        for i in 0..length {
+            let ox = a[0];
+            let oy = a[1];
+            Self::next_lightning(&mut rng2, &mut a);
+            let segment = [ox, oy, a[0], a[1]];
+            if !f(&segment) {
+                return i;
+            }
+        }
+        length
+    }
+
+    fn new_seed(rng: &mut ThreadRng) -> LightningSeed{
+        let mut seed: LightningSeed = Default::default();
+        rng.fill_bytes(&mut seed);
+        seed
+    }
+
+    /// Calls `f` with jagged segments between two points, reproducible by the seed.
+    fn arc(from: [f64; 2], to: [f64; 2], seed: &LightningSeed, f: &mut dyn FnMut(&[f64; 4])){
+        let mut rng2 = SmallRng::from_seed(*seed);
+        let delta = vec2_sub(to, from);
+        // Nothing to draw between enemies on top of each other, like the children of a splitter
+        if vec2_square_len(delta) < std::f64::EPSILON {
+            return;
+        }
+        let normal = vec2_normalized([-delta[1], delta[0]]);
+        let mut prev = from;
+        for i in 1..LIGHTNING_ARC_SEGMENTS + 1 {
+            let t = i as f64 / LIGHTNING_ARC_SEGMENTS as f64;
+            let jitter = if i == LIGHTNING_ARC_SEGMENTS { 0. } else { LIGHTNING_ACCEL * (rng2.gen::<f64>() - 0.5) };
+            let next = vec2_add(vec2_add(from, vec2_scale(delta, t)), vec2_scale(normal, jitter));
+            f(&[prev[0], prev[1], next[0], next[1]]);
+            prev = next;
+        }
+    }
+
+    /// Jumps from the enemy `first` to the nearest enemies not hit yet, up to
+    /// `hops` times, losing damage at each hop.  Returns the number of hops made.
+    pub fn chain(ctx: &mut FireContext, first: u32, mut damage: i32, hops: u32) -> u32{
+        let mut hit_ids = vec![first];
+        let mut from = match ctx.enemies.iter().find(|e| e.get_id() == first) {
+            Some(enemy) => enemy.get_base().pos,
+            None => return 0,
+        };
+        for hop in 0..hops {
+            damage = std::cmp::max(damage * LIGHTNING_CHAIN_FALLOFF / 100, 1);
+            let next = ctx.enemies.iter_mut()
+                .filter(|e| !hit_ids.contains(&e.get_id()))
+                .map(|e| (vec2_square_len(vec2_sub(e.get_base().pos, from)), e))
+                .filter(|(dist2, _)| *dist2 < LIGHTNING_CHAIN_RADIUS * LIGHTNING_CHAIN_RADIUS)
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            let enemy = match next {
+                Some((_, enemy)) => enemy,
+                None => return hop,
+            };
+            let to = enemy.get_base().pos;
+            enemy.damage(damage);
+            hit_ids.push(enemy.get_id());
+            ctx.hits.push(to);
+            let seed = Self::new_seed(ctx.rng);
+            ctx.effects.push(WeaponEffect::LightningArc{from, to, seed});
+            from = to;
+        }
+        hops
+    }
+}
+
+impl Weapon for LightningWeapon{
+    fn name(&self) -> &'static str { "Lightning" }
+    fn icon(&self) -> u32 { 4 }
+    fn color(&self) -> [f32; 3] { [1., 1., 0.] }
+    fn max_level(&self) -> u32 { 8 }
+    fn energy_cost(&self) -> u32 { 3 }
+
+    fn fire(&self, ctx: &mut FireContext, level: u32) -> u32{
+        let pos = ctx.pos;
+        // One more bolt every two levels, alternating in between
+        let nmax = std::cmp::min((level + 1 + ctx.time % 2) / 2, 31);
+
+        // Each bolt can jump to one more enemy every two levels
+        let hops = level / 2;
+
+        for _ in 0..nmax {
+            // Use the same seed twice to reproduce random sequence
+            let seed = Self::new_seed(ctx.rng);
+
+            let enemies = &mut ctx.enemies;
+            let rng = &mut ctx.rng;
+            let hits = &mut ctx.hits;
+            let mut first_hit = None;
+            let length = Self::walk(pos, &seed, LIGHTNING_VERTICES, &mut |segment: &[f64; 4]| {
+                let b = [segment[2], segment[3]];
+                let collider = Collider::segment([segment[0], segment[1]], b, 4.);
+                for enemy in enemies.iter_mut() {
+                    if enemy.hits(&collider) {
+                        let damage = 2 + rng.gen_range(0, 3);
+                        enemy.damage(damage);
+                        hits.push(b);
+                        first_hit = Some((enemy.get_id(), damage));
+                        return false;
+                    }
+                }
+                true
+            });
+            let hit = length != LIGHTNING_VERTICES;
+            ctx.effects.push(WeaponEffect::Lightning{pos, seed, length, hit});
+            if let Some((id, damage)) = first_hit {
+                Self::chain(ctx, id, damage, hops);
+            }
+        }
+        0
+    }
+
+    fn draw(&self, effects: &[WeaponEffect], context: &Context, graphics: &mut G2d){
+        let col = [1.,1.,1.,1.];
+        let col2 = [1.,0.5,1.,0.25];
+        for effect in effects {
+            if let WeaponEffect::Lightning{pos, seed, length, hit} = effect {
+                Self::walk(*pos, seed, *length, &mut |segment: &[f64; 4]| {
+                    line(if *hit { col } else { col2 }, if *hit { 2. } else { 1. }, *segment, context.transform, graphics);
+                    true
+                });
+            }
+            else if let WeaponEffect::LightningArc{from, to, seed} = effect {
+                Self::arc(*from, *to, seed, &mut |segment: &[f64; 4]| {
+                    line(col, 1.5, *segment, context.transform, graphics);
+                });
+            }
+        }
+    }
+}
+
+pub struct LockOnWeapon;
+
+impl LockOnWeapon{
+    pub fn laser_damage(level: u32) -> i32{
+        (4 + 2 * level) as i32
+    }
+
+    /// Where the reticle is after holding for `charge` frames.  It sweeps from
+    /// the ship to the top of the screen and starts over.
+    pub fn reticle_pos(pos: [f64; 2], charge: u32) -> [f64; 2]{
+        let range = pos[1].max(1.);
+        [pos[0], pos[1] - (charge as f64 * LOCK_SWEEP_SPEED) % range]
+    }
+}
+
+impl Weapon for LockOnWeapon{
+    fn name(&self) -> &'static str { "Lock-on" }
+    fn icon(&self) -> u32 { 8 }
+    fn color(&self) -> [f32; 3] { [1., 0.25, 0.5] }
+    fn max_level(&self) -> u32 { 6 }
+
+    fn fire(&self, _ctx: &mut FireContext, _level: u32) -> u32{
+        // Only fires on release
+        0
+    }
+
+    fn can_charge(&self) -> bool { true }
+
+    fn hold(&self, ctx: &mut FireContext, level: u32, charge: u32) -> Vec<u32>{
+        let reticle = Self::reticle_pos(ctx.pos, charge);
+        let capacity = (LOCK_MAX_TARGETS + level) as usize;
+        let damage = Self::laser_damage(level);
+        let reticle_collider = Shape::Circle(LOCK_RADIUS).at(reticle, 0.);
+        let mut locked = vec![];
+        for enemy in ctx.enemies.iter() {
+            if capacity <= ctx.player.locks.len() + locked.len() {
+                break;
+            }
+            let id = enemy.get_id();
+            let reservations = &ctx.reservations;
+            // Skip enemies already doomed by other shots like missiles
+            if enemy.hits(&reticle_collider) && !reservations.is_doomed(enemy)
+                && !ctx.player.locks.iter().any(|&l| reservations.target_of(l) == Some(id))
+            {
+                *ctx.id_gen += 1;
+                let lock = *ctx.id_gen;
+                ctx.reservations.reserve(lock, id, damage);
+                locked.push(lock);
+            }
+        }
+        for &lock in ctx.player.locks.iter().chain(locked.iter()) {
+            let target = ctx.reservations.target_of(lock);
+            if let Some(enemy) = ctx.enemies.iter().find(|e| Some(e.get_id()) == target) {
+                ctx.effects.push(WeaponEffect::Lock{pos: enemy.get_base().pos});
+            }
+        }
+        ctx.effects.push(WeaponEffect::Reticle{pos: reticle});
+        locked
+    }
+
+    fn release(&self, ctx: &mut FireContext, _level: u32, _charge: u32) -> u32{
+        let targets: Vec<(u32, i32)> = ctx.player.locks.iter().filter_map(|&lock| ctx.reservations.get(lock)).collect();
+        let volley = targets.len() as u32;
+        for (i, &(target, damage)) in targets.iter().enumerate() {
+            // Fan out first and let homing bring them around
+            let angle = std::f64::consts::PI * (i as f64 + 1.) / (volley as f64 + 1.);
+            let ent = Entity::new(ctx.id_gen, ctx.pos, [-LASER_SPEED * angle.cos(), -LASER_SPEED * angle.sin()])
+                .health(damage)
+                .blend(Blend::Add);
+            let projectile = Projectile::new_laser(BulletBase::new(ent), target, volley);
+            // The laser takes over the reservation of the lock
+            ctx.reservations.reserve(projectile.get_id(), target, damage);
+            ctx.bullets.insert(projectile.get_id(), projectile);
+        }
+        for &lock in ctx.player.locks.iter() {
+            ctx.reservations.release_shot(lock);
+        }
+        volley
+    }
+
+    fn draw(&self, effects: &[WeaponEffect], context: &Context, graphics: &mut G2d){
+        let color = [1., 0.25, 0.5, 1.];
+        for effect in effects {
+            match effect {
+                WeaponEffect::Reticle{pos} => {
+                    let r = LOCK_RADIUS;
+                    circle_arc(color, 1., 0., 2. * std::f64::consts::PI, [pos[0] - r, pos[1] - r, r * 2., r * 2.],
+                        context.transform, graphics);
+                    line(color, 0.5, [pos[0] - r, pos[1], pos[0] + r, pos[1]], context.transform, graphics);
+                    line(color, 0.5, [pos[0], pos[1] - r, pos[0], pos[1] + r], context.transform, graphics);
+                },
+                WeaponEffect::Lock{pos} => {
+                    let r = 10.;
+                    Rectangle::new_border(color, 1.).draw([pos[0] - r, pos[1] - r, r * 2., r * 2.],
+                        &context.draw_state, context.transform, graphics);
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Forgets locks on enemies that died or left the screen while the fire key is
+/// held, so that they neither take up lock slots nor get lasers on release.
+/// Call it before `hold` and `release` every frame.
+pub fn drop_dead_locks(player: &mut Player, enemies: &[Enemy], reservations: &mut Reservations){
+    player.locks.retain(|&lock| {
+        let target = reservations.target_of(lock);
+        let alive = enemies.iter().any(|e| Some(e.get_id()) == target);
+        if !alive {
+            reservations.release_shot(lock);
+        }
+        alive
+    });
+}
+
+/// Releases the damage reserved by locks that will never be fired, e.g. when
+/// the player dies or switches weapons while holding.
+pub fn cancel_locks(player: &mut Player, reservations: &mut Reservations){
+    for lock in player.locks.drain(..) {
+        reservations.release_shot(lock);
+    }
+}
+
+/// Ship at [100, 300] and whatever it shoots at, for firing weapons in tests.
+#[cfg(test)]
+struct Field{
+    id_gen: u32,
+    player: Player,
+    enemies: Vec<Enemy>,
+    bullets: HashMap<u32, Projectile>,
+    reservations: Reservations,
+    rng: ThreadRng,
+}
+
+#[cfg(test)]
+impl Field{
+    fn new() -> Self{
+        let mut id_gen = 0;
+        let player = Player::new(Entity::new(&mut id_gen, [100., 300.], [0., 0.]));
+        Self{id_gen, player, enemies: vec![], bullets: HashMap::new(), reservations: Reservations::new(), rng: thread_rng()}
+    }
+
+    /// Adds a stationary enemy and returns its id.
+    fn spawn(&mut self, make: fn(&mut u32, [f64; 2], [f64; 2]) -> Enemy, pos: [f64; 2]) -> u32{
+        let enemy = make(&mut self.id_gen, pos, [0., 0.]);
+        let id = enemy.get_id();
+        self.enemies.push(enemy);
+        id
+    }
+
+    fn ctx(&mut self) -> FireContext<'_>{
+        FireContext::new(&self.player, &mut self.enemies, &mut self.reservations, &mut self.bullets,
+            &mut self.id_gen, &mut self.rng, 0)
+    }
+
+    fn health(&self) -> Vec<i32>{
+        self.enemies.iter().map(|e| e.get_base().health).collect()
+    }
+}
+
+#[test]
+fn test_registry() {
+    for (i, weapon) in WEAPONS.iter().enumerate() {
+        assert!(0 < weapon.max_level());
+        for other in WEAPONS[i + 1..].iter() {
+            assert_ne!(weapon.name(), other.name());
+            assert_ne!(weapon.icon(), other.icon());
+        }
+    }
+}
+
+#[test]
+fn test_fire() {
+    let mut f = Field::new();
+    f.spawn(Enemy::new_enemy1, [100., 100.]);
+
+    assert_eq!(BulletWeapon.fire(&mut f.ctx(), 2), 7);
+    assert_eq!(f.bullets.len(), 7);
+
+    // Three missiles at level 0, like the bullets
+    assert_eq!(MissileWeapon.fire(&mut f.ctx(), 0), 3);
+    assert_eq!(f.bullets.len(), 10);
+
+    // The light beam damages the enemy right above the player
+    let health = f.health();
+    let mut ctx = f.ctx();
+    LightWeapon.fire(&mut ctx, 1);
+    assert_eq!(ctx.hits.len(), 1);
+    assert_eq!(ctx.effects.len(), 1);
+    assert_eq!(f.health(), vec![health[0] - 2]);
+}
+
+#[test]
+fn test_fire_option() {
+    let mut f = Field::new();
+
+    let mut ctx = f.ctx();
+    ctx.pos = [50., 250.];
+    for weapon in WEAPONS.iter() {
+        weapon.fire_option(&mut ctx, 4);
+    }
+    assert_eq!(f.bullets.len(), 2);
+    assert!(f.bullets.values().all(|b| b.get_base().ent.pos == [50., 250.]));
+}
+
+#[test]
+fn test_charge_shot() {
+    let mut f = Field::new();
+    f.spawn(Enemy::new_boss, [100., 200.]);
+    f.spawn(Enemy::new_boss, [100., 100.]);
+
+    {
+        let mut ctx = f.ctx();
+        assert_eq!(BulletWeapon.release(&mut ctx, 0, CHARGE_THRESHOLD - 1), 0);
+        assert_eq!(LightWeapon.release(&mut ctx, 0, CHARGE_MAX), 0);
+        assert_eq!(BulletWeapon.release(&mut ctx, 0, CHARGE_MAX), 1);
+    }
+    let mut shot = f.bullets.drain().next().unwrap().1;
+    let damage = shot.get_base().ent.health;
+    assert!(1 < damage);
+
+    // Pierces through both enemies, hitting each only once
+    let mut dummy = Entity::new(&mut f.id_gen, [0., 0.], [0., 0.]);
+    while shot.animate_bullet(&mut f.enemies, &mut dummy).is_none() {}
+    assert_eq!(f.health(), vec![64 - damage, 64 - damage]);
+}
+
+#[test]
+fn test_lock_on() {
+    let mut f = Field::new();
+    let first = f.spawn(Enemy::new_boss, [100., 250.]);
+    let second = f.spawn(Enemy::new_boss, [100., 200.]);
+    let aside = f.spawn(Enemy::new_boss, [300., 200.]);
+
+    // Sweep the reticle up to the top, locking on the enemies in its path
+    let level = 0;
+    for charge in 0..(300. / LOCK_SWEEP_SPEED) as u32 {
+        let locked = LockOnWeapon.hold(&mut f.ctx(), level, charge);
+        f.player.locks.extend(locked);
+    }
+    assert_eq!(f.player.locks.len(), 2);
+    let damage = LockOnWeapon::laser_damage(level);
+    assert_eq!(f.reservations.get(f.player.locks[0]), Some((first, damage)));
+    assert_eq!(f.reservations.reserved(first), damage);
+    assert_eq!(f.reservations.reserved(aside), 0);
+
+    assert_eq!(LockOnWeapon.release(&mut f.ctx(), level, 0), 2);
+    assert!(f.bullets.values().all(|b| if let Projectile::Laser{volley: 2, ..} = b { true } else { false }));
+    // The lasers take over the reservations of the locks
+    assert!(f.player.locks.iter().all(|&lock| f.reservations.get(lock).is_none()));
+    assert!(f.bullets.keys().all(|&id| f.reservations.get(id).is_some()));
+    assert_eq!(f.reservations.reserved(first), damage);
+    f.player.locks.clear();
+
+    // Lasers home in on their targets and give back the reservation on hit
+    let mut dummy = Entity::new(&mut f.id_gen, [0., 0.], [0., 0.]);
+    for (id, mut laser) in f.bullets.drain() {
+        while laser.animate_bullet(&mut f.enemies, &mut dummy).is_none() {}
+        f.reservations.release_shot(id);
+    }
+    assert_eq!(f.reservations.reserved(first), 0);
+    assert_eq!(f.reservations.reserved(second), 0);
+    assert!(f.health()[..2].iter().all(|&health| health < 64));
+
+    // Cancelled locks also give back the reservation
+    let locked = {
+        let mut ctx = f.ctx();
+        ctx.pos = [300., 300.];
+        LockOnWeapon.hold(&mut ctx, level, (100. / LOCK_SWEEP_SPEED) as u32)
+    };
+    f.player.locks.extend(locked);
+    assert_eq!(f.reservations.reserved(aside), damage);
+    cancel_locks(&mut f.player, &mut f.reservations);
+    assert!(f.player.locks.is_empty());
+    assert_eq!(f.reservations.reserved(aside), 0);
+}
+
+#[test]
+fn test_lock_on_dead_target() {
+    let mut f = Field::new();
+    f.spawn(Enemy::new_boss, [100., 250.]);
+    f.spawn(Enemy::new_boss, [100., 200.]);
+    let third = f.spawn(Enemy::new_boss, [100., 150.]);
+    let level = 0;
+    let sweep = |f: &mut Field, charges: std::ops::Range<u32>| {
+        for charge in charges {
+            drop_dead_locks(&mut f.player, &f.enemies, &mut f.reservations);
+            let locked = LockOnWeapon.hold(&mut f.ctx(), level, charge);
+            f.player.locks.extend(locked);
+        }
+    };
+
+    // The first two enemies take up all the lock slots
+    let reach = (110. / LOCK_SWEEP_SPEED) as u32;
+    sweep(&mut f, 0..reach);
+    assert_eq!(f.player.locks.len(), LOCK_MAX_TARGETS as usize);
+    assert_eq!(f.reservations.reserved(third), 0);
+
+    // A locked enemy dies while holding, freeing its slot for the third one
+    let dead = f.enemies.remove(0).get_id();
+    sweep(&mut f, reach..reach * 2);
+    assert_eq!(f.player.locks.len(), LOCK_MAX_TARGETS as usize);
+    assert_eq!(f.reservations.reserved(dead), 0);
+    assert!(0 < f.reservations.reserved(third));
+
+    assert_eq!(LockOnWeapon.release(&mut f.ctx(), level, 0), 2);
+    assert!(f.bullets.values().all(|b| if let Projectile::Laser{target, volley: 2, ..} = b { *target != dead } else { false }));
+}
+
+#[test]
+fn test_lock_on_reserved() {
+    let mut f = Field::new();
+    let near = f.spawn(Enemy::new_enemy1, [100., 250.]);
+    let far = f.spawn(Enemy::new_enemy1, [100., 200.]);
+
+    // A missile in flight is going to finish off the nearer one
+    f.reservations.reserve(1000, near, 5);
+    let mut locks = vec![];
+    for charge in 0..(150. / LOCK_SWEEP_SPEED) as u32 {
+        locks.extend(LockOnWeapon.hold(&mut f.ctx(), 0, charge));
+    }
+    assert_eq!(locks.len(), 1);
+    assert_eq!(f.reservations.target_of(locks[0]), Some(far));
+    assert_eq!(f.reservations.reserved(near), 5);
+}
+
+#[test]
+fn test_lightning_chain() {
+    let mut f = Field::new();
+    let step = LIGHTNING_CHAIN_RADIUS - 1.;
+    let first = f.spawn(Enemy::new_boss, [100., 100.]);
+    f.spawn(Enemy::new_boss, [100. + step, 100.]);
+    f.spawn(Enemy::new_boss, [100. + step * 2., 100.]);
+    // Out of reach from any other
+    f.spawn(Enemy::new_boss, [100., 100. + step * 2.]);
+
+    let mut ctx = f.ctx();
+    // Never jumps back to an enemy already hit
+    assert_eq!(LightningWeapon::chain(&mut ctx, first, 10, 5), 2);
+    assert_eq!(ctx.effects.len(), 2);
+
+    let damage1 = 10 * LIGHTNING_CHAIN_FALLOFF / 100;
+    let damage2 = damage1 * LIGHTNING_CHAIN_FALLOFF / 100;
+    assert_eq!(f.health(), vec![64, 64 - damage1, 64 - damage2, 64]);
+}
+
+#[test]
+fn test_lightning_overlap() {
+    let mut f = Field::new();
+    let first = f.spawn(Enemy::new_boss, [100., 100.]);
+    f.spawn(Enemy::new_boss, [100., 100.]);
+
+    let mut ctx = f.ctx();
+    assert_eq!(LightningWeapon::chain(&mut ctx, first, 10, 5), 1);
+    assert_eq!(ctx.effects.len(), 1);
+    let mut segments = vec![];
+    for effect in &ctx.effects {
+        if let WeaponEffect::LightningArc{from, to, seed} = effect {
+            LightningWeapon::arc(*from, *to, seed, &mut |seg| segments.push(*seg));
+        }
+    }
+    assert!(segments.iter().all(|seg| seg.iter().all(|x| x.is_finite())));
+}
+
+#[test]
+fn test_light_pierce() {
+    let mut f = Field::new();
+    f.spawn(Enemy::new_boss, [100., 100.]);
+    f.spawn(Enemy::new_boss, [100., 200.]);
+
+    let end_of = |ctx: &FireContext| match ctx.effects.last() {
+        Some(WeaponEffect::Beam{end, impact: true, ..}) => *end,
+        _ => panic!("beam didn't hit"),
+    };
+
+    // Stops at the nearest enemy
+    {
+        let mut ctx = f.ctx();
+        LightWeapon.fire(&mut ctx, 0);
+        assert_eq!(end_of(&ctx), 200. + BOSS_SIZE);
+    }
+    assert_eq!(f.health(), vec![64, 63]);
+
+    // Pierces one enemy at a higher level
+    {
+        let mut ctx = f.ctx();
+        LightWeapon.fire(&mut ctx, LIGHT_PIERCE_LEVELS);
+        assert_eq!(end_of(&ctx), 100. + BOSS_SIZE);
+    }
+    assert!(f.health()[0] < 64);
+
+    // Shields are never pierced
+    f.enemies.clear();
+    f.spawn(Enemy::new_enemy1, [100., 100.]);
+    f.spawn(|id_gen, pos, velo| Enemy::ShieldedBoss(super::entity::ShieldedBoss::new(id_gen, pos, velo)), [100., 200.]);
+    let mut ctx = f.ctx();
+    LightWeapon.fire(&mut ctx, LIGHT_PIERCE_LEVELS * 2);
+    assert_eq!(end_of(&ctx), 200. + 64.);
+}